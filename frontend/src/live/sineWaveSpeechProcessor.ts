@@ -187,30 +187,20 @@ class SineWaveSpeechProcessor extends AudioWorkletProcessor {
       throw new Error('Converter not initialized')
     }
     const C3_HZ = 130.81
-    const centsStep = 10
-    let hzToNormalizedCoef = (2 * Math.PI) / sampleRate
-
-    const indexToNormalizedFrequency = (i: number) => {
-      const cents = i * centsStep
-      const ratio = Math.pow(2, cents / 1200)
-      const hz = C3_HZ * ratio
-      const normalized = hz * hzToNormalizedCoef
-      return normalized
-    }
+    const nPoints = 121 // One point every 10 cents
 
-    const originalFrequencies = new Float32Array(
-      range(0, 1200 / centsStep + 1).map(indexToNormalizedFrequency)
-    )
     const versions = range(0, 101)
       .map((i) => (3 * i) / 100)
       .map((s) => {
-        const array = this.converter!.quantize_frequencies_continuous(
-          originalFrequencies,
+        const curve = this.converter!.quantization_curve_continuous(
+          C3_HZ,
+          C3_HZ * 2,
+          nPoints,
           s
         )
         return {
           strength: s,
-          values: Array.from(array).map((f) => f / hzToNormalizedCoef),
+          values: Array.from(curve.slice(nPoints)),
         }
       })
 
@@ -2,28 +2,402 @@
 /* eslint-disable */
 /**
 */
+export enum NoteName {
+  C = 0,
+  CSharp = 1,
+  D = 2,
+  DSharp = 3,
+  E = 4,
+  F = 5,
+  FSharp = 6,
+  G = 7,
+  GSharp = 8,
+  A = 9,
+  ASharp = 10,
+  B = 11,
+}
+/**
+*/
 export enum FrequencyQuantizationType {
   Chromatic = 0,
   Diatonic = 1,
   Pentatonic = 2,
 }
 /**
+*/
+export enum WindowType {
+  Hann = 0,
+  Hamming = 1,
+  Blackman = 2,
+/**
+* Standard deviation of 0.4 times half the window size.
+*/
+  Gaussian = 3,
+/**
+* Rises slowly and falls quickly, so the analysis reacts to the newest samples sooner.
+*/
+  LowLatency = 4,
+}
+/**
+* What drives the all-pole filter of the LPC vocoder.
+*/
+export enum Excitation {
+/**
+* The residual of the LPC fit, which gives back (roughly) the original audio.
+*/
+  Residual = 0,
+/**
+* A pulse train at a fixed pitch, for a robot voice.
+*/
+  PulseTrain = 1,
+/**
+* White noise, for a whisper.
+*/
+  Noise = 2,
+/**
+* An external signal, e.g. a synth, like a talkbox.
+*/
+  Carrier = 3,
+}
+/**
+*/
+export enum Temperament {
+/**
+* 12-tone equal temperament, the default.
+*/
+  Equal = 0,
+/**
+* 5-limit just intonation relative to the tonic.
+*/
+  JustIntonation = 1,
+  Pythagorean = 2,
+  QuarterCommaMeantone = 3,
+}
+/**
+* Which poles become waves when the LPC fit finds more poles than there are waves.
+*/
+export enum PoleSelection {
+/**
+* The lowest frequencies, which is what you get with an LPC order of `2 * n_waves`.
+*/
+  Lowest = 0,
+/**
+* The largest magnitudes.
+*/
+  Strongest = 1,
+/**
+* The smallest bandwidths, i.e. the sharpest peaks of the envelope.
+*/
+  Narrowest = 2,
+}
+/**
+* How `get_frequencies_and_magnitudes()` finds the waves.
+*/
+export enum Analyzer {
+/**
+* Formants from the poles of an LPC fit, the classic sine wave speech.
+*/
+  Lpc = 0,
+/**
+* Spectral peaks continued into tracks, better for music and singing.
+* See `sinusoidal::SinusoidalAnalyzer`.
+*/
+  Sinusoidal = 1,
+/**
+* Formants from the peaks of the cepstrally smoothed spectrum, more robust than LPC
+* for high voices. See `cepstrum::CepstralAnalyzer`.
+*/
+  Cepstral = 2,
+}
+/**
+*/
+export enum FormantOffsetUnit {
+  Hz = 0,
+  Semitones = 1,
+}
+/**
+* How loud the wave of each formant is.
+*
+* All estimators except `PoleGain` are in the same units as the (pre-emphasized) input
+* audio and don't depend on the number of waves, the hop size or the window size.
+*/
+export enum MagnitudeEstimator {
+/**
+* `gain / (1 - |pole|)`, the original estimator. Explodes for poles close to the unit
+* circle, which is why the converter divides it by the number of waves and synthesis
+* compresses the output.
+*/
+  PoleGain = 0,
+/**
+* The LPC envelope `gain / |A(e^{jw})|` at the pole frequency: the spectral level
+* of the formant peak, relative to white noise with the same RMS as the residual.
+*/
+  Envelope = 1,
+/**
+* `gain * sqrt(2 / (1 - |pole|))`, proportional to the square root of the energy under
+* the resonance peak, so narrow peaks don't dominate.
+*/
+  BandwidthNormalized = 2,
+/**
+* The amplitude of a sine wave with the same energy as the input spectrum between the
+* neighboring formants, see `band_energy_magnitudes()`. Poles are selected using
+* the envelope since this needs the audio.
+*/
+  BandEnergy = 3,
+}
+/**
+*/
+export enum ScaleMode {
+  Major = 0,
+  NaturalMinor = 1,
+  HarmonicMinor = 2,
+  MelodicMinor = 3,
+  Dorian = 4,
+  Phrygian = 5,
+  Lydian = 6,
+  Mixolydian = 7,
+  Locrian = 8,
+  MajorPentatonic = 9,
+  MinorPentatonic = 10,
+  Blues = 11,
+  WholeTone = 12,
+}
+/**
+* What a tuner would show for one wave.
+*/
+export class NoteAnalysis {
+  free(): void;
+/**
+* The note name with the octave, e.g. "C#4".
+* @returns {string}
+*/
+  label(): string;
+/**
+* Positive if the frequency is above the nearest note.
+*/
+  cents_offset: number;
+/**
+*/
+  frequency_hz: number;
+/**
+* The nearest note of the tuning.
+*/
+  midi_note: number;
+/**
+*/
+  note_name: NoteName;
+/**
+* Octave in scientific pitch notation, so MIDI note 60 is C4.
+*/
+  octave: number;
+/**
+* Index of the wave within the frame.
+*/
+  wave: number;
+}
+/**
 * Note that the converter doesn't care about the sample rate,
 */
 export class SineWaveSpeechConverter {
   free(): void;
 /**
-* @param {number} n_waves
-* @param {number} hop_size
-* @param {number} sample_rate
-* @returns {SineWaveSpeechConverter}
+* Use a 12-note tuning with A4 at `a4_frequency` Hz. For temperaments other than equal,
+* `tonic` is the note that the pure intervals are relative to.
+* @param {Temperament} temperament
+* @param {NoteName} tonic
+* @param {number} a4_frequency
 */
-  static new(n_waves: number, hop_size: number, sample_rate: number): SineWaveSpeechConverter;
+  set_tuning(temperament: Temperament, tonic: NoteName, a4_frequency: number): void;
 /**
+* Returns the synthesized audio followed by the last phase of each wave.
+* When `time_stretch` is not 1, the number of audio samples can vary between calls
+* because the fractional part of the synthesis hop is carried over to the next call,
+* so take the audio as everything except the last `n_waves` values.
+* @param {Float32Array} frequencies
+* @param {Float32Array} magnitudes
+* @param {Float32Array} first_phases
+* @returns {Float32Array}
+*/
+  synthesize(frequencies: Float32Array, magnitudes: Float32Array, first_phases: Float32Array): Float32Array;
+/**
+* Export the waves as a Standard MIDI File with one track (and channel) per wave,
+* so with at most 15 waves. Each wave is split into notes of the nearest MIDI pitch
+* in 12-TET with A4 = 440 Hz, ignoring the tuning, with the velocity given by the
+* magnitude. Frames quieter than `silence_threshold_db` relative to the loudest
+* frame are rests. With `pitch_bend`, the exact frequencies are kept as pitch bends
+* with a range of 2 semitones.
+* @param {Float32Array} frequencies
+* @param {Float32Array} magnitudes
+* @param {boolean} pitch_bend
+* @param {number} silence_threshold_db
+* @returns {Uint8Array}
+*/
+  export_midi(frequencies: Float32Array, magnitudes: Float32Array, pitch_bend: boolean, silence_threshold_db: number): Uint8Array;
+/**
+* Instead of sine waves, resynthesize the audio with a full LPC vocoder: the same
+* analysis as `get_frequencies_and_magnitudes()` gives an all-pole filter per frame,
+* which is driven by `excitation`. `pulse_frequency_hz` is only used by the pulse train
+* (0 or less means unvoiced, i.e. noise) and `carrier` (same length as the audio,
+* zero-padded if shorter) only by the carrier excitation.
+* Returns one output sample per input sample, up to a multiple of `hop_size`.
 * @param {Float32Array} audio_samples
+* @param {Excitation} excitation
+* @param {number} pulse_frequency_hz
+* @param {Float32Array | undefined} [carrier]
 * @returns {Float32Array}
 */
-  get_frequencies_and_magnitudes(audio_samples: Float32Array): Float32Array;
+  lpc_vocoder(audio_samples: Float32Array, excitation: Excitation, pulse_frequency_hz: number, carrier?: Float32Array): Float32Array;
+/**
+* Choose between LPC, sinusoidal and cepstral analysis. The analysis config (see
+* `set_analysis_config()`) applies to all of them, except that sinusoidal analysis
+* uses no pre-emphasis and only LPC uses the order and the frequency warping.
+* LPC and cepstral analysis return the waves of each hop sorted by frequency (except
+* for waves with bands, see `set_frequency_bands()`). Sinusoidal analysis, and any
+* analysis with `set_track_continuity()`, keeps each track in its wave instead, so
+* per-wave settings like the offsets of `transform_formants()`, `fold_registers()`
+* and `add_depth()` follow the tracks.
+* @param {Analyzer} analyzer
+*/
+  set_analyzer(analyzer: Analyzer): void;
+/**
+* Tuner-style analysis of the last frame of `frequencies`: the nearest note of the
+* current tuning for each wave and how far off it is. Silent waves (frequency or
+* magnitude 0, e.g. a track that is fading out) are left out, use `NoteAnalysis.wave`
+* to tell the waves apart.
+* @param {Float32Array} frequencies
+* @param {Float32Array} magnitudes
+* @returns {(NoteAnalysis)[]}
+*/
+  analyze_notes(frequencies: Float32Array, magnitudes: Float32Array): (NoteAnalysis)[];
+/**
+* Octave-folds each wave into its own range, e.g. the first wave into C3-C4 and the second
+* into C4-C5, which keeps the pitch classes but avoids shrill or muddy registers.
+* `ranges_hz` is a flattened list of (low, high) pairs with 0 < low < high, one per wave.
+* Waves without a range are left alone.
+* @param {Float32Array} frequencies
+* @param {Float32Array} ranges_hz
+* @returns {Float32Array}
+*/
+  fold_registers(frequencies: Float32Array, ranges_hz: Float32Array): Float32Array;
+/**
+* Like `set_key()`, but with a user-supplied scale given as semitones above the root.
+* @param {NoteName} root
+* @param {Int32Array} intervals
+*/
+  set_custom_key(root: NoteName, intervals: Int32Array): void;
+/**
+* The spectral envelope that the LPC fit models for the last frame of the audio, for
+* visualization. The envelope is evaluated at `n_points` frequencies between `min_hz`
+* and `max_hz`, spaced evenly in Hz or, if `log_frequency_grid` is set, in cents.
+*
+* Returns the frequencies in Hz followed by the envelope. If `include_spectrum` is set,
+* the magnitude spectrum of the analyzed (windowed and pre-emphasized) audio follows,
+* scaled to be comparable with the envelope.
+* @param {Float32Array} audio_samples
+* @param {number} min_hz
+* @param {number} max_hz
+* @param {number} n_points
+* @param {boolean} log_frequency_grid
+* @param {boolean} include_spectrum
+* @returns {Float32Array}
+*/
+  get_lpc_envelope(audio_samples: Float32Array, min_hz: number, max_hz: number, n_points: number, log_frequency_grid: boolean, include_spectrum: boolean): Float32Array;
+/**
+* The residual of the LPC fit done by `get_frequencies_and_magnitudes()`, i.e. what's
+* left of the audio once the formants are removed. Normalized to unit RMS per frame.
+* @param {Float32Array} audio_samples
+* @returns {Float32Array}
+*/
+  get_lpc_residual(audio_samples: Float32Array): Float32Array;
+/**
+* Use a tuning from the contents of a Scala `.scl` file and optionally a `.kbm`
+* keyboard mapping. `a4_frequency` is only used without a `.kbm` file, otherwise
+* the reference frequency of the mapping wins. Unless the result is a plain 12-note
+* tuning, quantization ignores the key and snaps to all notes of the scale.
+* @param {string} scl
+* @param {string | undefined} kbm
+* @param {number} a4_frequency
+*/
+  set_scala_tuning(scl: string, kbm: string | undefined, a4_frequency: number): void;
+/**
+* Either major or natural minor, `None` until the estimator has heard something.
+* @returns {ScaleMode | undefined}
+*/
+  estimated_key_mode(): ScaleMode | undefined;
+/**
+* `None` until the estimator has heard something.
+* @returns {NoteName | undefined}
+*/
+  estimated_key_root(): NoteName | undefined;
+/**
+* The mapping from input to output frequency of `quantize_frequencies()` with the current
+* key and tuning, for plotting. Returns `n_points` input frequencies in Hz, evenly spaced
+* in cents between `min_hz` and `max_hz`, followed by the corresponding outputs in Hz.
+* @param {number} min_hz
+* @param {number} max_hz
+* @param {number} n_points
+* @param {FrequencyQuantizationType | undefined} [quantization_type]
+* @returns {Float32Array}
+*/
+  quantization_curve(min_hz: number, max_hz: number, n_points: number, quantization_type?: FrequencyQuantizationType): Float32Array;
+/**
+* Choose which poles become waves, which matters when `lpc_order` (see
+* `set_analysis_config()`) is higher than twice the number of waves. Only poles between
+* `min_hz` and `max_hz` with a bandwidth of at most `max_bandwidth_hz` are considered.
+* @param {PoleSelection} selection
+* @param {number} min_hz
+* @param {number} max_hz
+* @param {number | undefined} [max_bandwidth_hz]
+*/
+  set_pole_selection(selection: PoleSelection, min_hz: number, max_hz: number, max_bandwidth_hz?: number): void;
+/**
+* Shift formants, meant to be applied between analysis and synthesis.
+* `scale` multiplies all frequencies (vocal tract length change, e.g. ~1.4 for adult->child),
+* `warping_curve` is a flattened list of (input Hz, output Hz) pairs
+* and `offsets` contains one offset per wave.
+* @param {Float32Array} frequencies
+* @param {number} scale
+* @param {Float32Array} warping_curve
+* @param {Float32Array} offsets
+* @param {FormantOffsetUnit} offset_unit
+* @returns {Float32Array}
+*/
+  transform_formants(frequencies: Float32Array, scale: number, warping_curve: Float32Array, offsets: Float32Array, offset_unit: FormantOffsetUnit): Float32Array;
+/**
+* Pass in raw MIDI bytes, e.g. from a Web MIDI `midimessage` event.
+* Note-on and note-off messages update the notes used by
+* `quantize_frequencies_to_held_notes()`, everything else is ignored.
+* @param {Uint8Array} bytes
+*/
+  handle_midi_message(bytes: Uint8Array): void;
+/**
+* Configure how the audio is analyzed. `window_size` defaults to twice the hop size and
+* must be at least the hop size. `pre_emphasis` is 0.9 by default, 0 turns it off.
+* `lpc_order` defaults to twice the number of waves. A higher order finds more
+* poles than there are waves and only some of them are kept.
+* @param {WindowType} window_type
+* @param {number | undefined} window_size
+* @param {number} pre_emphasis
+* @param {number | undefined} [lpc_order]
+*/
+  set_analysis_config(window_type: WindowType, window_size: number | undefined, pre_emphasis: number, lpc_order?: number): void;
+/**
+* Constrain each wave to a frequency band, e.g. [200, 1000, 600, 3000, 1500, 4000]
+* for F1, F2 and F3. `bands_hz` is a flattened list of (low, high) pairs with
+* 0 < low < high, one per wave.
+* Each wave takes the best pole in its band (see `set_pole_selection()`) and is
+* silent if there is none. Waves without a band take the best remaining poles.
+* @param {Float32Array} bands_hz
+*/
+  set_frequency_bands(bands_hz: Float32Array): void;
+/**
+* Configure `retune()`. A wave only switches to a new note once the unquantized frequency
+* is `hysteresis_cents` past the boundary between the old note and the new one, and then
+* glides to the new note over `retune_seconds`. Zero for both gives plain quantization.
+* @param {number} hysteresis_cents
+* @param {number} retune_seconds
+*/
+  set_retune_settings(hysteresis_cents: number, retune_seconds: number): void;
 /**
 * @param {Float32Array} frequencies
 * @param {FrequencyQuantizationType | undefined} [quantization_type]
@@ -31,12 +405,210 @@ export class SineWaveSpeechConverter {
 */
   quantize_frequencies(frequencies: Float32Array, quantization_type?: FrequencyQuantizationType): Float32Array;
 /**
+* Turn each wave into a track that follows the nearest pole (at most
+* `max_deviation_cents` away) and fades out and in when its pole disappears or
+* appears, instead of gliding, see `tracks::TrackContinuity`. None turns it off.
+* The waves are then no longer sorted by frequency.
+* @param {number | undefined} [max_deviation_cents]
+*/
+  set_track_continuity(max_deviation_cents?: number): void;
+/**
+* Configure the cepstral analyzer. The F0 estimate of each frame is searched between
+* `min_f0_hz` and `max_f0_hz` and sets the lifter cutoff just below the pitch period,
+* unless `lifter_quefrency_ms` sets the cutoff explicitly.
+* @param {number} min_f0_hz
+* @param {number} max_f0_hz
+* @param {number | undefined} [lifter_quefrency_ms]
+*/
+  set_cepstral_analysis(min_f0_hz: number, max_f0_hz: number, lifter_quefrency_ms?: number): void;
+/**
+* Analyze on a warped frequency axis, which gives low frequencies more resolution.
+* `warping` is the allpass warping factor between -1 and 1, 0 means no warping.
+* The frequencies returned by `get_frequencies_and_magnitudes()` are unwarped.
+* `lpc_vocoder()` always uses unwarped analysis.
+* @param {number} warping
+*/
+  set_frequency_warping(warping: number): void;
+/**
+* Fit LPC on windows of two pitch periods centred on the glottal epochs instead of on
+* fixed hops, then resample the formant tracks onto the hops. With high voices, a
+* fixed window spans several periods and the poles get pulled toward the harmonics.
+* The pitch is estimated as in `set_cepstral_analysis()`. Only affects LPC analysis.
+* Epochs aren't carried over between calls, so this works best on whole recordings.
+* @param {boolean} pitch_synchronous
+*/
+  set_pitch_synchronous(pitch_synchronous: boolean): void;
+/**
+* Choose how the magnitudes of the waves are estimated, see `MagnitudeEstimator`.
+* Only affects `Analyzer::Lpc`, the other analyzers measure the magnitudes directly.
+* @param {MagnitudeEstimator} estimator
+*/
+  set_magnitude_estimator(estimator: MagnitudeEstimator): void;
+/**
+* Configure the sinusoidal analyzer: a track continues to the nearest peak at most
+* `max_deviation_cents` away. Peaks more than `threshold_db` below the strongest
+* one of the frame are ignored.
+* @param {number} max_deviation_cents
+* @param {number} threshold_db
+*/
+  set_sinusoidal_analysis(max_deviation_cents: number, threshold_db: number): void;
+/**
+* Remove all targets of the morph used by `quantize_frequencies_morph()`,
+* to be followed by `add_quantization_morph_target()` calls.
+*/
+  clear_quantization_morph(): void;
+/**
+* Correlation between the heard pitch classes and the key profile, between -1 and 1.
+* @returns {number}
+*/
+  estimated_key_confidence(): number;
+/**
+* Divide the octave into `divisions` equal steps. Since this is not a 12-note tuning,
+* quantization ignores the key and snaps to all steps.
+* @param {number} divisions
+* @param {NoteName} tonic
+* @param {number} a4_frequency
+*/
+  set_equal_division_tuning(divisions: number, tonic: NoteName, a4_frequency: number): void;
+/**
+* How long (roughly) the key estimator remembers what it heard.
+* @param {number} seconds
+*/
+  set_key_estimation_memory(seconds: number): void;
+/**
+* Set the scale/chord timeline used by `quantize_frequencies_timeline()`.
+* See `timeline::Timeline` for the JSON format.
+* @param {string} json
+*/
+  set_quantization_timeline(json: string): void;
+/**
+* Morph between the quantization targets. The quantized versions are averaged in the
+* cents domain, weighted by the targets' weight curves at `position`.
+* @param {Float32Array} frequencies
+* @param {number} position
+* @returns {Float32Array}
+*/
+  quantize_frequencies_morph(frequencies: Float32Array, position: number): Float32Array;
+/**
+* Like `set_frequency_warping()` with the warping that approximates the Bark scale
+* at the converter's sample rate.
+*/
+  set_bark_frequency_warping(): void;
+/**
+*/
+  clear_quantization_timeline(): void;
+/**
+* Add a target to the morph used by `quantize_frequencies_morph()`. `None` means
+* unquantized. `weight_curve` is a flattened list of (morph position, weight) pairs.
+* If it's empty, the target peaks at position i for the i-th target and crossfades
+* linearly with its neighbors.
+* @param {FrequencyQuantizationType | undefined} quantization_type
+* @param {Float32Array} weight_curve
+*/
+  add_quantization_morph_target(quantization_type: FrequencyQuantizationType | undefined, weight_curve: Float32Array): void;
+/**
+* Like `quantization_curve()`, but for `quantize_frequencies_continuous()`.
+* @param {number} min_hz
+* @param {number} max_hz
+* @param {number} n_points
+* @param {number} quantization_strength
+* @returns {Float32Array}
+*/
+  quantization_curve_continuous(min_hz: number, max_hz: number, n_points: number, quantization_strength: number): Float32Array;
+/**
+* Like `quantize_frequencies()`, but each frame uses the key that the timeline
+* has at that point of the output. `start_seconds` is the output time of the first
+* frame, so that a file can be rendered in chunks. Before the first change of the
+* timeline (or without a timeline), the current key is used.
+* @param {Float32Array} frequencies
+* @param {FrequencyQuantizationType | undefined} quantization_type
+* @param {number} start_seconds
+* @returns {Float32Array}
+*/
+  quantize_frequencies_timeline(frequencies: Float32Array, quantization_type: FrequencyQuantizationType | undefined, start_seconds: number): Float32Array;
+/**
+* @param {Float32Array} audio_samples
+* @returns {Float32Array}
+*/
+  get_frequencies_and_magnitudes(audio_samples: Float32Array): Float32Array;
+/**
+* Feed input audio to the key estimator. Unless `key_locked` is set,
+* the estimated key is then used for quantization.
+* @param {Float32Array} audio_samples
+*/
+  update_key_estimate_from_audio(audio_samples: Float32Array): void;
+/**
+* The original quantization slider, a preset of `quantize_frequencies_morph()`:
+* 0 is unquantized, 1 chromatic, 2 diatonic and 3 pentatonic.
 * @param {Float32Array} frequencies
 * @param {number} quantization_strength
 * @returns {Float32Array}
 */
   quantize_frequencies_continuous(frequencies: Float32Array, quantization_strength: number): Float32Array;
 /**
+* Like `quantize_frequencies()`, but no two waves of a frame get the same note,
+* so n waves always give an n-note chord. The waves keep their order and the total
+* deviation in cents is minimized. With a positive `voice_leading_weight`, moving away
+* from the previous frame's notes is penalized (per cent, relative to the deviation).
+* @param {Float32Array} frequencies
+* @param {FrequencyQuantizationType} quantization_type
+* @param {number} voice_leading_weight
+* @returns {Float32Array}
+*/
+  quantize_frequencies_polyphonic(frequencies: Float32Array, quantization_type: FrequencyQuantizationType, voice_leading_weight: number): Float32Array;
+/**
+* Like `update_key_estimate_from_audio()`, but uses the formant tracks.
+* @param {Float32Array} frequencies
+* @param {Float32Array} magnitudes
+*/
+  update_key_estimate_from_tracks(frequencies: Float32Array, magnitudes: Float32Array): void;
+/**
+* Snaps each wave to the pitch classes of the currently held notes, in any octave.
+* Holding a chord harmonizes the speech with it, like a vocoder.
+* If no notes are held, the frequencies are returned unchanged.
+* @param {Float32Array} frequencies
+* @returns {Float32Array}
+*/
+  quantize_frequencies_to_held_notes(frequencies: Float32Array): Float32Array;
+/**
+* Like `add_quantization_morph_target()`, but snaps to any scale, given as semitones
+* above the root.
+* @param {NoteName} root
+* @param {Int32Array} intervals
+* @param {Float32Array} weight_curve
+*/
+  add_quantization_morph_scale_target(root: NoteName, intervals: Int32Array, weight_curve: Float32Array): void;
+/**
+* @param {number} n_waves
+* @param {number} hop_size
+* @param {number} sample_rate
+* @returns {SineWaveSpeechConverter}
+*/
+  static new(n_waves: number, hop_size: number, sample_rate: number): SineWaveSpeechConverter;
+/**
+* Forget the state kept between calls, e.g. when starting a new file.
+*/
+  reset(): void;
+/**
+* Make quantization stateful, see `set_retune_settings()`. Takes the frequencies before
+* and after any of the quantization methods, so it works with all of them.
+* `quantization_type` is the type used with the current key, so that a note that is no
+* longer in the key isn't held. Pass None when quantizing to something else,
+* e.g. held notes or a timeline, and notes are then held regardless.
+* @param {Float32Array} unquantized
+* @param {Float32Array} quantized
+* @param {FrequencyQuantizationType | undefined} [quantization_type]
+* @returns {Float32Array}
+*/
+  retune(unquantized: Float32Array, quantized: Float32Array, quantization_type?: FrequencyQuantizationType): Float32Array;
+/**
+* Set the key that quantization snaps to. Diatonic quantization uses all the notes
+* of the scale and pentatonic quantization uses a five-note subset.
+* @param {NoteName} root
+* @param {ScaleMode} mode
+*/
+  set_key(root: NoteName, mode: ScaleMode): void;
+/**
 * Add depth by lowering the first frequencies more than the last ones.
 * Specifically, the first frequency is lowered by `width` octaves, and the last frequency
 * is left unchanged. The deepening of the other frequencies is a linear interpolation.
@@ -46,43 +618,108 @@ export class SineWaveSpeechConverter {
 */
   add_depth(frequencies: Float32Array, width: number): Float32Array;
 /**
-* @param {Float32Array} frequencies
-* @param {Float32Array} magnitudes
-* @param {Float32Array} first_phases
-* @returns {Float32Array}
 */
-  synthesize(frequencies: Float32Array, magnitudes: Float32Array, first_phases: Float32Array): Float32Array;
+  hop_size: number;
 /**
+* If false, the key estimated by the `update_key_estimate_*()` methods
+* is used for quantization. Set to true to keep the current key.
 */
-  hop_size: number;
+  key_locked: boolean;
 /**
 */
   n_waves: number;
 /**
 */
   sample_rate: number;
+/**
+* Synthesis uses a hop size of `hop_size * time_stretch`, so values above 1
+* slow the speech down. Can be fractional.
+*/
+  time_stretch: number;
 }
 
 export type InitInput = RequestInfo | URL | Response | BufferSource | WebAssembly.Module;
 
 export interface InitOutput {
   readonly memory: WebAssembly.Memory;
-  readonly __wbg_sinewavespeechconverter_free: (a: number) => void;
-  readonly __wbg_get_sinewavespeechconverter_n_waves: (a: number) => number;
-  readonly __wbg_set_sinewavespeechconverter_n_waves: (a: number, b: number) => void;
+  readonly __wbg_get_noteanalysis_cents_offset: (a: number) => number;
+  readonly __wbg_get_noteanalysis_frequency_hz: (a: number) => number;
+  readonly __wbg_get_noteanalysis_midi_note: (a: number) => number;
+  readonly __wbg_get_noteanalysis_note_name: (a: number) => number;
+  readonly __wbg_get_noteanalysis_octave: (a: number) => number;
+  readonly __wbg_get_noteanalysis_wave: (a: number) => number;
+  readonly __wbg_noteanalysis_free: (a: number) => void;
+  readonly __wbg_set_noteanalysis_cents_offset: (a: number, b: number) => void;
+  readonly __wbg_set_noteanalysis_frequency_hz: (a: number, b: number) => void;
+  readonly __wbg_set_noteanalysis_midi_note: (a: number, b: number) => void;
+  readonly __wbg_set_noteanalysis_note_name: (a: number, b: number) => void;
+  readonly __wbg_set_noteanalysis_octave: (a: number, b: number) => void;
+  readonly __wbg_set_noteanalysis_wave: (a: number, b: number) => void;
+  readonly noteanalysis_label: (a: number, b: number) => void;
   readonly __wbg_get_sinewavespeechconverter_hop_size: (a: number) => number;
-  readonly __wbg_set_sinewavespeechconverter_hop_size: (a: number, b: number) => void;
+  readonly __wbg_get_sinewavespeechconverter_key_locked: (a: number) => number;
+  readonly __wbg_get_sinewavespeechconverter_n_waves: (a: number) => number;
   readonly __wbg_get_sinewavespeechconverter_sample_rate: (a: number) => number;
+  readonly __wbg_get_sinewavespeechconverter_time_stretch: (a: number) => number;
+  readonly __wbg_set_sinewavespeechconverter_hop_size: (a: number, b: number) => void;
+  readonly __wbg_set_sinewavespeechconverter_key_locked: (a: number, b: number) => void;
+  readonly __wbg_set_sinewavespeechconverter_n_waves: (a: number, b: number) => void;
   readonly __wbg_set_sinewavespeechconverter_sample_rate: (a: number, b: number) => void;
-  readonly sinewavespeechconverter_new: (a: number, b: number, c: number) => number;
+  readonly __wbg_set_sinewavespeechconverter_time_stretch: (a: number, b: number) => void;
+  readonly __wbg_sinewavespeechconverter_free: (a: number) => void;
+  readonly sinewavespeechconverter_add_depth: (a: number, b: number, c: number, d: number, e: number) => void;
+  readonly sinewavespeechconverter_add_quantization_morph_scale_target: (a: number, b: number, c: number, d: number, e: number, f: number) => void;
+  readonly sinewavespeechconverter_add_quantization_morph_target: (a: number, b: number, c: number, d: number) => void;
+  readonly sinewavespeechconverter_analyze_notes: (a: number, b: number, c: number, d: number, e: number, f: number) => void;
+  readonly sinewavespeechconverter_clear_quantization_morph: (a: number) => void;
+  readonly sinewavespeechconverter_clear_quantization_timeline: (a: number) => void;
+  readonly sinewavespeechconverter_estimated_key_confidence: (a: number) => number;
+  readonly sinewavespeechconverter_estimated_key_mode: (a: number) => number;
+  readonly sinewavespeechconverter_estimated_key_root: (a: number) => number;
+  readonly sinewavespeechconverter_export_midi: (a: number, b: number, c: number, d: number, e: number, f: number, g: number, h: number) => void;
+  readonly sinewavespeechconverter_fold_registers: (a: number, b: number, c: number, d: number, e: number, f: number) => void;
   readonly sinewavespeechconverter_get_frequencies_and_magnitudes: (a: number, b: number, c: number, d: number) => void;
+  readonly sinewavespeechconverter_get_lpc_envelope: (a: number, b: number, c: number, d: number, e: number, f: number, g: number, h: number, i: number) => void;
+  readonly sinewavespeechconverter_get_lpc_residual: (a: number, b: number, c: number, d: number) => void;
+  readonly sinewavespeechconverter_handle_midi_message: (a: number, b: number, c: number) => void;
+  readonly sinewavespeechconverter_lpc_vocoder: (a: number, b: number, c: number, d: number, e: number, f: number, g: number, h: number) => void;
+  readonly sinewavespeechconverter_new: (a: number, b: number, c: number) => number;
+  readonly sinewavespeechconverter_quantization_curve: (a: number, b: number, c: number, d: number, e: number, f: number) => void;
+  readonly sinewavespeechconverter_quantization_curve_continuous: (a: number, b: number, c: number, d: number, e: number, f: number) => void;
   readonly sinewavespeechconverter_quantize_frequencies: (a: number, b: number, c: number, d: number, e: number) => void;
   readonly sinewavespeechconverter_quantize_frequencies_continuous: (a: number, b: number, c: number, d: number, e: number) => void;
-  readonly sinewavespeechconverter_add_depth: (a: number, b: number, c: number, d: number, e: number) => void;
+  readonly sinewavespeechconverter_quantize_frequencies_morph: (a: number, b: number, c: number, d: number, e: number) => void;
+  readonly sinewavespeechconverter_quantize_frequencies_polyphonic: (a: number, b: number, c: number, d: number, e: number, f: number) => void;
+  readonly sinewavespeechconverter_quantize_frequencies_timeline: (a: number, b: number, c: number, d: number, e: number, f: number) => void;
+  readonly sinewavespeechconverter_quantize_frequencies_to_held_notes: (a: number, b: number, c: number, d: number) => void;
+  readonly sinewavespeechconverter_reset: (a: number) => void;
+  readonly sinewavespeechconverter_retune: (a: number, b: number, c: number, d: number, e: number, f: number, g: number) => void;
+  readonly sinewavespeechconverter_set_analysis_config: (a: number, b: number, c: number, d: number, e: number, f: number, g: number) => void;
+  readonly sinewavespeechconverter_set_analyzer: (a: number, b: number) => void;
+  readonly sinewavespeechconverter_set_bark_frequency_warping: (a: number) => void;
+  readonly sinewavespeechconverter_set_cepstral_analysis: (a: number, b: number, c: number, d: number, e: number) => void;
+  readonly sinewavespeechconverter_set_custom_key: (a: number, b: number, c: number, d: number) => void;
+  readonly sinewavespeechconverter_set_equal_division_tuning: (a: number, b: number, c: number, d: number) => void;
+  readonly sinewavespeechconverter_set_frequency_bands: (a: number, b: number, c: number) => void;
+  readonly sinewavespeechconverter_set_frequency_warping: (a: number, b: number) => void;
+  readonly sinewavespeechconverter_set_key: (a: number, b: number, c: number) => void;
+  readonly sinewavespeechconverter_set_key_estimation_memory: (a: number, b: number) => void;
+  readonly sinewavespeechconverter_set_magnitude_estimator: (a: number, b: number) => void;
+  readonly sinewavespeechconverter_set_pitch_synchronous: (a: number, b: number) => void;
+  readonly sinewavespeechconverter_set_pole_selection: (a: number, b: number, c: number, d: number, e: number, f: number) => void;
+  readonly sinewavespeechconverter_set_quantization_timeline: (a: number, b: number, c: number, d: number) => void;
+  readonly sinewavespeechconverter_set_retune_settings: (a: number, b: number, c: number) => void;
+  readonly sinewavespeechconverter_set_scala_tuning: (a: number, b: number, c: number, d: number, e: number, f: number, g: number) => void;
+  readonly sinewavespeechconverter_set_sinusoidal_analysis: (a: number, b: number, c: number) => void;
+  readonly sinewavespeechconverter_set_track_continuity: (a: number, b: number, c: number) => void;
+  readonly sinewavespeechconverter_set_tuning: (a: number, b: number, c: number, d: number) => void;
   readonly sinewavespeechconverter_synthesize: (a: number, b: number, c: number, d: number, e: number, f: number, g: number, h: number) => void;
+  readonly sinewavespeechconverter_transform_formants: (a: number, b: number, c: number, d: number, e: number, f: number, g: number, h: number, i: number, j: number) => void;
+  readonly sinewavespeechconverter_update_key_estimate_from_audio: (a: number, b: number, c: number) => void;
+  readonly sinewavespeechconverter_update_key_estimate_from_tracks: (a: number, b: number, c: number, d: number, e: number) => void;
   readonly __wbindgen_add_to_stack_pointer: (a: number) => number;
-  readonly __wbindgen_malloc: (a: number, b: number) => number;
   readonly __wbindgen_free: (a: number, b: number, c: number) => void;
+  readonly __wbindgen_malloc: (a: number, b: number) => number;
   readonly __wbindgen_realloc: (a: number, b: number, c: number, d: number) => number;
 }
 
@@ -39,6 +39,24 @@ function getStringFromWasm0(ptr, len) {
     return cachedTextDecoder.decode(getUint8Memory0().subarray(ptr, ptr + len));
 }
 
+function addHeapObject(obj) {
+    if (heap_next === heap.length) heap.push(heap.length + 1);
+    const idx = heap_next;
+    heap_next = heap[idx];
+
+    heap[idx] = obj;
+    return idx;
+}
+
+let cachedInt32Memory0 = null;
+
+function getInt32Memory0() {
+    if (cachedInt32Memory0 === null || cachedInt32Memory0.byteLength === 0) {
+        cachedInt32Memory0 = new Int32Array(wasm.memory.buffer);
+    }
+    return cachedInt32Memory0;
+}
+
 let cachedFloat32Memory0 = null;
 
 function getFloat32Memory0() {
@@ -57,31 +75,45 @@ function passArrayF32ToWasm0(arg, malloc) {
     return ptr;
 }
 
-let cachedInt32Memory0 = null;
-
-function getInt32Memory0() {
-    if (cachedInt32Memory0 === null || cachedInt32Memory0.byteLength === 0) {
-        cachedInt32Memory0 = new Int32Array(wasm.memory.buffer);
-    }
-    return cachedInt32Memory0;
-}
-
 function getArrayF32FromWasm0(ptr, len) {
     ptr = ptr >>> 0;
     return getFloat32Memory0().subarray(ptr / 4, ptr / 4 + len);
 }
 
+function getArrayU8FromWasm0(ptr, len) {
+    ptr = ptr >>> 0;
+    return getUint8Memory0().subarray(ptr / 1, ptr / 1 + len);
+}
+
 function isLikeNone(x) {
     return x === undefined || x === null;
 }
 
-function addHeapObject(obj) {
-    if (heap_next === heap.length) heap.push(heap.length + 1);
-    const idx = heap_next;
-    heap_next = heap[idx];
+let cachedUint32Memory0 = null;
 
-    heap[idx] = obj;
-    return idx;
+function getUint32Memory0() {
+    if (cachedUint32Memory0 === null || cachedUint32Memory0.byteLength === 0) {
+        cachedUint32Memory0 = new Uint32Array(wasm.memory.buffer);
+    }
+    return cachedUint32Memory0;
+}
+
+function getArrayJsValueFromWasm0(ptr, len) {
+    ptr = ptr >>> 0;
+    const mem = getUint32Memory0();
+    const slice = mem.subarray(ptr / 4, ptr / 4 + len);
+    const result = [];
+    for (let i = 0; i < slice.length; i++) {
+        result.push(takeObject(slice[i]));
+    }
+    return result;
+}
+
+function passArray32ToWasm0(arg, malloc) {
+    const ptr = malloc(arg.length * 4, 4) >>> 0;
+    getUint32Memory0().set(arg, ptr / 4);
+    WASM_VECTOR_LEN = arg.length;
+    return ptr;
 }
 
 const cachedTextEncoder = (typeof TextEncoder !== 'undefined' ? new TextEncoder('utf-8') : { encode: () => { throw Error('TextEncoder not available') } } );
@@ -137,9 +169,265 @@ function passStringToWasm0(arg, malloc, realloc) {
     WASM_VECTOR_LEN = offset;
     return ptr;
 }
+
+function passArray8ToWasm0(arg, malloc) {
+    const ptr = malloc(arg.length * 1, 1) >>> 0;
+    getUint8Memory0().set(arg, ptr / 1);
+    WASM_VECTOR_LEN = arg.length;
+    return ptr;
+}
+/**
+*/
+export const NoteName = Object.freeze({ C:0,"0":"C",CSharp:1,"1":"CSharp",D:2,"2":"D",DSharp:3,"3":"DSharp",E:4,"4":"E",F:5,"5":"F",FSharp:6,"6":"FSharp",G:7,"7":"G",GSharp:8,"8":"GSharp",A:9,"9":"A",ASharp:10,"10":"ASharp",B:11,"11":"B", });
 /**
 */
 export const FrequencyQuantizationType = Object.freeze({ Chromatic:0,"0":"Chromatic",Diatonic:1,"1":"Diatonic",Pentatonic:2,"2":"Pentatonic", });
+/**
+*/
+export const WindowType = Object.freeze({ Hann:0,"0":"Hann",Hamming:1,"1":"Hamming",Blackman:2,"2":"Blackman",
+/**
+* Standard deviation of 0.4 times half the window size.
+*/
+Gaussian:3,"3":"Gaussian",
+/**
+* Rises slowly and falls quickly, so the analysis reacts to the newest samples sooner.
+*/
+LowLatency:4,"4":"LowLatency", });
+/**
+* What drives the all-pole filter of the LPC vocoder.
+*/
+export const Excitation = Object.freeze({
+/**
+* The residual of the LPC fit, which gives back (roughly) the original audio.
+*/
+Residual:0,"0":"Residual",
+/**
+* A pulse train at a fixed pitch, for a robot voice.
+*/
+PulseTrain:1,"1":"PulseTrain",
+/**
+* White noise, for a whisper.
+*/
+Noise:2,"2":"Noise",
+/**
+* An external signal, e.g. a synth, like a talkbox.
+*/
+Carrier:3,"3":"Carrier", });
+/**
+*/
+export const Temperament = Object.freeze({
+/**
+* 12-tone equal temperament, the default.
+*/
+Equal:0,"0":"Equal",
+/**
+* 5-limit just intonation relative to the tonic.
+*/
+JustIntonation:1,"1":"JustIntonation",Pythagorean:2,"2":"Pythagorean",QuarterCommaMeantone:3,"3":"QuarterCommaMeantone", });
+/**
+* Which poles become waves when the LPC fit finds more poles than there are waves.
+*/
+export const PoleSelection = Object.freeze({
+/**
+* The lowest frequencies, which is what you get with an LPC order of `2 * n_waves`.
+*/
+Lowest:0,"0":"Lowest",
+/**
+* The largest magnitudes.
+*/
+Strongest:1,"1":"Strongest",
+/**
+* The smallest bandwidths, i.e. the sharpest peaks of the envelope.
+*/
+Narrowest:2,"2":"Narrowest", });
+/**
+* How `get_frequencies_and_magnitudes()` finds the waves.
+*/
+export const Analyzer = Object.freeze({
+/**
+* Formants from the poles of an LPC fit, the classic sine wave speech.
+*/
+Lpc:0,"0":"Lpc",
+/**
+* Spectral peaks continued into tracks, better for music and singing.
+* See `sinusoidal::SinusoidalAnalyzer`.
+*/
+Sinusoidal:1,"1":"Sinusoidal",
+/**
+* Formants from the peaks of the cepstrally smoothed spectrum, more robust than LPC
+* for high voices. See `cepstrum::CepstralAnalyzer`.
+*/
+Cepstral:2,"2":"Cepstral", });
+/**
+*/
+export const FormantOffsetUnit = Object.freeze({ Hz:0,"0":"Hz",Semitones:1,"1":"Semitones", });
+/**
+* How loud the wave of each formant is.
+*
+* All estimators except `PoleGain` are in the same units as the (pre-emphasized) input
+* audio and don't depend on the number of waves, the hop size or the window size.
+*/
+export const MagnitudeEstimator = Object.freeze({
+/**
+* `gain / (1 - |pole|)`, the original estimator. Explodes for poles close to the unit
+* circle, which is why the converter divides it by the number of waves and synthesis
+* compresses the output.
+*/
+PoleGain:0,"0":"PoleGain",
+/**
+* The LPC envelope `gain / |A(e^{jw})|` at the pole frequency: the spectral level
+* of the formant peak, relative to white noise with the same RMS as the residual.
+*/
+Envelope:1,"1":"Envelope",
+/**
+* `gain * sqrt(2 / (1 - |pole|))`, proportional to the square root of the energy under
+* the resonance peak, so narrow peaks don't dominate.
+*/
+BandwidthNormalized:2,"2":"BandwidthNormalized",
+/**
+* The amplitude of a sine wave with the same energy as the input spectrum between the
+* neighboring formants, see `band_energy_magnitudes()`. Poles are selected using
+* the envelope since this needs the audio.
+*/
+BandEnergy:3,"3":"BandEnergy", });
+/**
+*/
+export const ScaleMode = Object.freeze({ Major:0,"0":"Major",NaturalMinor:1,"1":"NaturalMinor",HarmonicMinor:2,"2":"HarmonicMinor",MelodicMinor:3,"3":"MelodicMinor",Dorian:4,"4":"Dorian",Phrygian:5,"5":"Phrygian",Lydian:6,"6":"Lydian",Mixolydian:7,"7":"Mixolydian",Locrian:8,"8":"Locrian",MajorPentatonic:9,"9":"MajorPentatonic",MinorPentatonic:10,"10":"MinorPentatonic",Blues:11,"11":"Blues",WholeTone:12,"12":"WholeTone", });
+
+const NoteAnalysisFinalization = (typeof FinalizationRegistry === 'undefined')
+    ? { register: () => {}, unregister: () => {} }
+    : new FinalizationRegistry(ptr => wasm.__wbg_noteanalysis_free(ptr >>> 0));
+/**
+* What a tuner would show for one wave.
+*/
+export class NoteAnalysis {
+
+    static __wrap(ptr) {
+        ptr = ptr >>> 0;
+        const obj = Object.create(NoteAnalysis.prototype);
+        obj.__wbg_ptr = ptr;
+        NoteAnalysisFinalization.register(obj, obj.__wbg_ptr, obj);
+        return obj;
+    }
+
+    __destroy_into_raw() {
+        const ptr = this.__wbg_ptr;
+        this.__wbg_ptr = 0;
+        NoteAnalysisFinalization.unregister(this);
+        return ptr;
+    }
+
+    free() {
+        const ptr = this.__destroy_into_raw();
+        wasm.__wbg_noteanalysis_free(ptr);
+    }
+    /**
+    * The note name with the octave, e.g. "C#4".
+    * @returns {string}
+    */
+    label() {
+        let deferred1_0;
+        let deferred1_1;
+        try {
+            const retptr = wasm.__wbindgen_add_to_stack_pointer(-16);
+            wasm.noteanalysis_label(retptr, this.__wbg_ptr);
+            var r0 = getInt32Memory0()[retptr / 4 + 0];
+            var r1 = getInt32Memory0()[retptr / 4 + 1];
+            deferred1_0 = r0;
+            deferred1_1 = r1;
+            return getStringFromWasm0(r0, r1);
+        } finally {
+            wasm.__wbindgen_add_to_stack_pointer(16);
+            wasm.__wbindgen_free(deferred1_0, deferred1_1, 1);
+        }
+    }
+    /**
+    * Index of the wave within the frame.
+    * @returns {number}
+    */
+    get wave() {
+        const ret = wasm.__wbg_get_noteanalysis_wave(this.__wbg_ptr);
+        return ret >>> 0;
+    }
+    /**
+    * Index of the wave within the frame.
+    * @param {number} arg0
+    */
+    set wave(arg0) {
+        wasm.__wbg_set_noteanalysis_wave(this.__wbg_ptr, arg0);
+    }
+    /**
+    * @returns {number}
+    */
+    get frequency_hz() {
+        const ret = wasm.__wbg_get_noteanalysis_frequency_hz(this.__wbg_ptr);
+        return ret;
+    }
+    /**
+    * @param {number} arg0
+    */
+    set frequency_hz(arg0) {
+        wasm.__wbg_set_noteanalysis_frequency_hz(this.__wbg_ptr, arg0);
+    }
+    /**
+    * The nearest note of the tuning.
+    * @returns {number}
+    */
+    get midi_note() {
+        const ret = wasm.__wbg_get_noteanalysis_midi_note(this.__wbg_ptr);
+        return ret;
+    }
+    /**
+    * The nearest note of the tuning.
+    * @param {number} arg0
+    */
+    set midi_note(arg0) {
+        wasm.__wbg_set_noteanalysis_midi_note(this.__wbg_ptr, arg0);
+    }
+    /**
+    * @returns {NoteName}
+    */
+    get note_name() {
+        const ret = wasm.__wbg_get_noteanalysis_note_name(this.__wbg_ptr);
+        return ret;
+    }
+    /**
+    * @param {NoteName} arg0
+    */
+    set note_name(arg0) {
+        wasm.__wbg_set_noteanalysis_note_name(this.__wbg_ptr, arg0);
+    }
+    /**
+    * Octave in scientific pitch notation, so MIDI note 60 is C4.
+    * @returns {number}
+    */
+    get octave() {
+        const ret = wasm.__wbg_get_noteanalysis_octave(this.__wbg_ptr);
+        return ret;
+    }
+    /**
+    * Octave in scientific pitch notation, so MIDI note 60 is C4.
+    * @param {number} arg0
+    */
+    set octave(arg0) {
+        wasm.__wbg_set_noteanalysis_octave(this.__wbg_ptr, arg0);
+    }
+    /**
+    * Positive if the frequency is above the nearest note.
+    * @returns {number}
+    */
+    get cents_offset() {
+        const ret = wasm.__wbg_get_noteanalysis_cents_offset(this.__wbg_ptr);
+        return ret;
+    }
+    /**
+    * Positive if the frequency is above the nearest note.
+    * @param {number} arg0
+    */
+    set cents_offset(arg0) {
+        wasm.__wbg_set_noteanalysis_cents_offset(this.__wbg_ptr, arg0);
+    }
+}
 
 const SineWaveSpeechConverterFinalization = (typeof FinalizationRegistry === 'undefined')
     ? { register: () => {}, unregister: () => {} }
@@ -208,91 +496,270 @@ export class SineWaveSpeechConverter {
         wasm.__wbg_set_sinewavespeechconverter_sample_rate(this.__wbg_ptr, arg0);
     }
     /**
-    * @param {number} n_waves
-    * @param {number} hop_size
-    * @param {number} sample_rate
-    * @returns {SineWaveSpeechConverter}
+    * Synthesis uses a hop size of `hop_size * time_stretch`, so values above 1
+    * slow the speech down. Can be fractional.
+    * @returns {number}
     */
-    static new(n_waves, hop_size, sample_rate) {
-        const ret = wasm.sinewavespeechconverter_new(n_waves, hop_size, sample_rate);
-        return SineWaveSpeechConverter.__wrap(ret);
+    get time_stretch() {
+        const ret = wasm.__wbg_get_sinewavespeechconverter_time_stretch(this.__wbg_ptr);
+        return ret;
     }
     /**
-    * @param {Float32Array} audio_samples
+    * Synthesis uses a hop size of `hop_size * time_stretch`, so values above 1
+    * slow the speech down. Can be fractional.
+    * @param {number} arg0
+    */
+    set time_stretch(arg0) {
+        wasm.__wbg_set_sinewavespeechconverter_time_stretch(this.__wbg_ptr, arg0);
+    }
+    /**
+    * If false, the key estimated by the `update_key_estimate_*()` methods
+    * is used for quantization. Set to true to keep the current key.
+    * @returns {boolean}
+    */
+    get key_locked() {
+        const ret = wasm.__wbg_get_sinewavespeechconverter_key_locked(this.__wbg_ptr);
+        return ret !== 0;
+    }
+    /**
+    * If false, the key estimated by the `update_key_estimate_*()` methods
+    * is used for quantization. Set to true to keep the current key.
+    * @param {boolean} arg0
+    */
+    set key_locked(arg0) {
+        wasm.__wbg_set_sinewavespeechconverter_key_locked(this.__wbg_ptr, arg0);
+    }
+    /**
+    * Use a 12-note tuning with A4 at `a4_frequency` Hz. For temperaments other than equal,
+    * `tonic` is the note that the pure intervals are relative to.
+    * @param {Temperament} temperament
+    * @param {NoteName} tonic
+    * @param {number} a4_frequency
+    */
+    set_tuning(temperament, tonic, a4_frequency) {
+        wasm.sinewavespeechconverter_set_tuning(this.__wbg_ptr, temperament, tonic, a4_frequency);
+    }
+    /**
+    * Returns the synthesized audio followed by the last phase of each wave.
+    * When `time_stretch` is not 1, the number of audio samples can vary between calls
+    * because the fractional part of the synthesis hop is carried over to the next call,
+    * so take the audio as everything except the last `n_waves` values.
+    * @param {Float32Array} frequencies
+    * @param {Float32Array} magnitudes
+    * @param {Float32Array} first_phases
     * @returns {Float32Array}
     */
-    get_frequencies_and_magnitudes(audio_samples) {
+    synthesize(frequencies, magnitudes, first_phases) {
         try {
             const retptr = wasm.__wbindgen_add_to_stack_pointer(-16);
-            const ptr0 = passArrayF32ToWasm0(audio_samples, wasm.__wbindgen_malloc);
+            const ptr0 = passArrayF32ToWasm0(frequencies, wasm.__wbindgen_malloc);
             const len0 = WASM_VECTOR_LEN;
-            wasm.sinewavespeechconverter_get_frequencies_and_magnitudes(retptr, this.__wbg_ptr, ptr0, len0);
+            const ptr1 = passArrayF32ToWasm0(magnitudes, wasm.__wbindgen_malloc);
+            const len1 = WASM_VECTOR_LEN;
+            const ptr2 = passArrayF32ToWasm0(first_phases, wasm.__wbindgen_malloc);
+            const len2 = WASM_VECTOR_LEN;
+            wasm.sinewavespeechconverter_synthesize(retptr, this.__wbg_ptr, ptr0, len0, ptr1, len1, ptr2, len2);
             var r0 = getInt32Memory0()[retptr / 4 + 0];
             var r1 = getInt32Memory0()[retptr / 4 + 1];
-            var v2 = getArrayF32FromWasm0(r0, r1).slice();
+            var v4 = getArrayF32FromWasm0(r0, r1).slice();
             wasm.__wbindgen_free(r0, r1 * 4, 4);
-            return v2;
+            return v4;
         } finally {
             wasm.__wbindgen_add_to_stack_pointer(16);
         }
     }
     /**
+    * Export the waves as a Standard MIDI File with one track (and channel) per wave,
+    * so with at most 15 waves. Each wave is split into notes of the nearest MIDI pitch
+    * in 12-TET with A4 = 440 Hz, ignoring the tuning, with the velocity given by the
+    * magnitude. Frames quieter than `silence_threshold_db` relative to the loudest
+    * frame are rests. With `pitch_bend`, the exact frequencies are kept as pitch bends
+    * with a range of 2 semitones.
     * @param {Float32Array} frequencies
-    * @param {FrequencyQuantizationType | undefined} [quantization_type]
-    * @returns {Float32Array}
+    * @param {Float32Array} magnitudes
+    * @param {boolean} pitch_bend
+    * @param {number} silence_threshold_db
+    * @returns {Uint8Array}
     */
-    quantize_frequencies(frequencies, quantization_type) {
+    export_midi(frequencies, magnitudes, pitch_bend, silence_threshold_db) {
         try {
             const retptr = wasm.__wbindgen_add_to_stack_pointer(-16);
             const ptr0 = passArrayF32ToWasm0(frequencies, wasm.__wbindgen_malloc);
             const len0 = WASM_VECTOR_LEN;
-            wasm.sinewavespeechconverter_quantize_frequencies(retptr, this.__wbg_ptr, ptr0, len0, isLikeNone(quantization_type) ? 3 : quantization_type);
+            const ptr1 = passArrayF32ToWasm0(magnitudes, wasm.__wbindgen_malloc);
+            const len1 = WASM_VECTOR_LEN;
+            wasm.sinewavespeechconverter_export_midi(retptr, this.__wbg_ptr, ptr0, len0, ptr1, len1, pitch_bend, silence_threshold_db);
             var r0 = getInt32Memory0()[retptr / 4 + 0];
             var r1 = getInt32Memory0()[retptr / 4 + 1];
-            var v2 = getArrayF32FromWasm0(r0, r1).slice();
-            wasm.__wbindgen_free(r0, r1 * 4, 4);
-            return v2;
+            var r2 = getInt32Memory0()[retptr / 4 + 2];
+            var r3 = getInt32Memory0()[retptr / 4 + 3];
+            if (r3) {
+                throw takeObject(r2);
+            }
+            var v3 = getArrayU8FromWasm0(r0, r1).slice();
+            wasm.__wbindgen_free(r0, r1 * 1, 1);
+            return v3;
         } finally {
             wasm.__wbindgen_add_to_stack_pointer(16);
         }
     }
     /**
-    * @param {Float32Array} frequencies
-    * @param {number} quantization_strength
+    * Instead of sine waves, resynthesize the audio with a full LPC vocoder: the same
+    * analysis as `get_frequencies_and_magnitudes()` gives an all-pole filter per frame,
+    * which is driven by `excitation`. `pulse_frequency_hz` is only used by the pulse train
+    * (0 or less means unvoiced, i.e. noise) and `carrier` (same length as the audio,
+    * zero-padded if shorter) only by the carrier excitation.
+    * Returns one output sample per input sample, up to a multiple of `hop_size`.
+    * @param {Float32Array} audio_samples
+    * @param {Excitation} excitation
+    * @param {number} pulse_frequency_hz
+    * @param {Float32Array | undefined} [carrier]
     * @returns {Float32Array}
     */
-    quantize_frequencies_continuous(frequencies, quantization_strength) {
+    lpc_vocoder(audio_samples, excitation, pulse_frequency_hz, carrier) {
         try {
             const retptr = wasm.__wbindgen_add_to_stack_pointer(-16);
-            const ptr0 = passArrayF32ToWasm0(frequencies, wasm.__wbindgen_malloc);
+            const ptr0 = passArrayF32ToWasm0(audio_samples, wasm.__wbindgen_malloc);
             const len0 = WASM_VECTOR_LEN;
-            wasm.sinewavespeechconverter_quantize_frequencies_continuous(retptr, this.__wbg_ptr, ptr0, len0, quantization_strength);
+            var ptr1 = isLikeNone(carrier) ? 0 : passArrayF32ToWasm0(carrier, wasm.__wbindgen_malloc);
+            var len1 = WASM_VECTOR_LEN;
+            wasm.sinewavespeechconverter_lpc_vocoder(retptr, this.__wbg_ptr, ptr0, len0, excitation, pulse_frequency_hz, ptr1, len1);
             var r0 = getInt32Memory0()[retptr / 4 + 0];
             var r1 = getInt32Memory0()[retptr / 4 + 1];
-            var v2 = getArrayF32FromWasm0(r0, r1).slice();
+            var r2 = getInt32Memory0()[retptr / 4 + 2];
+            var r3 = getInt32Memory0()[retptr / 4 + 3];
+            if (r3) {
+                throw takeObject(r2);
+            }
+            var v3 = getArrayF32FromWasm0(r0, r1).slice();
             wasm.__wbindgen_free(r0, r1 * 4, 4);
-            return v2;
+            return v3;
         } finally {
             wasm.__wbindgen_add_to_stack_pointer(16);
         }
     }
     /**
-    * Add depth by lowering the first frequencies more than the last ones.
-    * Specifically, the first frequency is lowered by `width` octaves, and the last frequency
-    * is left unchanged. The deepening of the other frequencies is a linear interpolation.
+    * Choose between LPC, sinusoidal and cepstral analysis. The analysis config (see
+    * `set_analysis_config()`) applies to all of them, except that sinusoidal analysis
+    * uses no pre-emphasis and only LPC uses the order and the frequency warping.
+    * LPC and cepstral analysis return the waves of each hop sorted by frequency (except
+    * for waves with bands, see `set_frequency_bands()`). Sinusoidal analysis, and any
+    * analysis with `set_track_continuity()`, keeps each track in its wave instead, so
+    * per-wave settings like the offsets of `transform_formants()`, `fold_registers()`
+    * and `add_depth()` follow the tracks.
+    * @param {Analyzer} analyzer
+    */
+    set_analyzer(analyzer) {
+        wasm.sinewavespeechconverter_set_analyzer(this.__wbg_ptr, analyzer);
+    }
+    /**
+    * Tuner-style analysis of the last frame of `frequencies`: the nearest note of the
+    * current tuning for each wave and how far off it is. Silent waves (frequency or
+    * magnitude 0, e.g. a track that is fading out) are left out, use `NoteAnalysis.wave`
+    * to tell the waves apart.
     * @param {Float32Array} frequencies
-    * @param {number} width
-    * @returns {Float32Array}
+    * @param {Float32Array} magnitudes
+    * @returns {(NoteAnalysis)[]}
     */
-    add_depth(frequencies, width) {
+    analyze_notes(frequencies, magnitudes) {
         try {
             const retptr = wasm.__wbindgen_add_to_stack_pointer(-16);
             const ptr0 = passArrayF32ToWasm0(frequencies, wasm.__wbindgen_malloc);
             const len0 = WASM_VECTOR_LEN;
-            wasm.sinewavespeechconverter_add_depth(retptr, this.__wbg_ptr, ptr0, len0, width);
+            const ptr1 = passArrayF32ToWasm0(magnitudes, wasm.__wbindgen_malloc);
+            const len1 = WASM_VECTOR_LEN;
+            wasm.sinewavespeechconverter_analyze_notes(retptr, this.__wbg_ptr, ptr0, len0, ptr1, len1);
             var r0 = getInt32Memory0()[retptr / 4 + 0];
             var r1 = getInt32Memory0()[retptr / 4 + 1];
-            var v2 = getArrayF32FromWasm0(r0, r1).slice();
+            var v3 = getArrayJsValueFromWasm0(r0, r1).slice();
+            wasm.__wbindgen_free(r0, r1 * 4, 4);
+            return v3;
+        } finally {
+            wasm.__wbindgen_add_to_stack_pointer(16);
+        }
+    }
+    /**
+    * Octave-folds each wave into its own range, e.g. the first wave into C3-C4 and the second
+    * into C4-C5, which keeps the pitch classes but avoids shrill or muddy registers.
+    * `ranges_hz` is a flattened list of (low, high) pairs with 0 < low < high, one per wave.
+    * Waves without a range are left alone.
+    * @param {Float32Array} frequencies
+    * @param {Float32Array} ranges_hz
+    * @returns {Float32Array}
+    */
+    fold_registers(frequencies, ranges_hz) {
+        try {
+            const retptr = wasm.__wbindgen_add_to_stack_pointer(-16);
+            const ptr0 = passArrayF32ToWasm0(frequencies, wasm.__wbindgen_malloc);
+            const len0 = WASM_VECTOR_LEN;
+            const ptr1 = passArrayF32ToWasm0(ranges_hz, wasm.__wbindgen_malloc);
+            const len1 = WASM_VECTOR_LEN;
+            wasm.sinewavespeechconverter_fold_registers(retptr, this.__wbg_ptr, ptr0, len0, ptr1, len1);
+            var r0 = getInt32Memory0()[retptr / 4 + 0];
+            var r1 = getInt32Memory0()[retptr / 4 + 1];
+            var v3 = getArrayF32FromWasm0(r0, r1).slice();
+            wasm.__wbindgen_free(r0, r1 * 4, 4);
+            return v3;
+        } finally {
+            wasm.__wbindgen_add_to_stack_pointer(16);
+        }
+    }
+    /**
+    * Like `set_key()`, but with a user-supplied scale given as semitones above the root.
+    * @param {NoteName} root
+    * @param {Int32Array} intervals
+    */
+    set_custom_key(root, intervals) {
+        const ptr0 = passArray32ToWasm0(intervals, wasm.__wbindgen_malloc);
+        const len0 = WASM_VECTOR_LEN;
+        wasm.sinewavespeechconverter_set_custom_key(this.__wbg_ptr, root, ptr0, len0);
+    }
+    /**
+    * The spectral envelope that the LPC fit models for the last frame of the audio, for
+    * visualization. The envelope is evaluated at `n_points` frequencies between `min_hz`
+    * and `max_hz`, spaced evenly in Hz or, if `log_frequency_grid` is set, in cents.
+    *
+    * Returns the frequencies in Hz followed by the envelope. If `include_spectrum` is set,
+    * the magnitude spectrum of the analyzed (windowed and pre-emphasized) audio follows,
+    * scaled to be comparable with the envelope.
+    * @param {Float32Array} audio_samples
+    * @param {number} min_hz
+    * @param {number} max_hz
+    * @param {number} n_points
+    * @param {boolean} log_frequency_grid
+    * @param {boolean} include_spectrum
+    * @returns {Float32Array}
+    */
+    get_lpc_envelope(audio_samples, min_hz, max_hz, n_points, log_frequency_grid, include_spectrum) {
+        try {
+            const retptr = wasm.__wbindgen_add_to_stack_pointer(-16);
+            const ptr0 = passArrayF32ToWasm0(audio_samples, wasm.__wbindgen_malloc);
+            const len0 = WASM_VECTOR_LEN;
+            wasm.sinewavespeechconverter_get_lpc_envelope(retptr, this.__wbg_ptr, ptr0, len0, min_hz, max_hz, n_points, log_frequency_grid, include_spectrum);
+            var r0 = getInt32Memory0()[retptr / 4 + 0];
+            var r1 = getInt32Memory0()[retptr / 4 + 1];
+            var v2 = getArrayF32FromWasm0(r0, r1).slice();
+            wasm.__wbindgen_free(r0, r1 * 4, 4);
+            return v2;
+        } finally {
+            wasm.__wbindgen_add_to_stack_pointer(16);
+        }
+    }
+    /**
+    * The residual of the LPC fit done by `get_frequencies_and_magnitudes()`, i.e. what's
+    * left of the audio once the formants are removed. Normalized to unit RMS per frame.
+    * @param {Float32Array} audio_samples
+    * @returns {Float32Array}
+    */
+    get_lpc_residual(audio_samples) {
+        try {
+            const retptr = wasm.__wbindgen_add_to_stack_pointer(-16);
+            const ptr0 = passArrayF32ToWasm0(audio_samples, wasm.__wbindgen_malloc);
+            const len0 = WASM_VECTOR_LEN;
+            wasm.sinewavespeechconverter_get_lpc_residual(retptr, this.__wbg_ptr, ptr0, len0);
+            var r0 = getInt32Memory0()[retptr / 4 + 0];
+            var r1 = getInt32Memory0()[retptr / 4 + 1];
+            var v2 = getArrayF32FromWasm0(r0, r1).slice();
             wasm.__wbindgen_free(r0, r1 * 4, 4);
             return v2;
         } finally {
@@ -300,21 +767,104 @@ export class SineWaveSpeechConverter {
         }
     }
     /**
+    * Use a tuning from the contents of a Scala `.scl` file and optionally a `.kbm`
+    * keyboard mapping. `a4_frequency` is only used without a `.kbm` file, otherwise
+    * the reference frequency of the mapping wins. Unless the result is a plain 12-note
+    * tuning, quantization ignores the key and snaps to all notes of the scale.
+    * @param {string} scl
+    * @param {string | undefined} kbm
+    * @param {number} a4_frequency
+    */
+    set_scala_tuning(scl, kbm, a4_frequency) {
+        try {
+            const retptr = wasm.__wbindgen_add_to_stack_pointer(-16);
+            const ptr0 = passStringToWasm0(scl, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+            const len0 = WASM_VECTOR_LEN;
+            var ptr1 = isLikeNone(kbm) ? 0 : passStringToWasm0(kbm, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+            var len1 = WASM_VECTOR_LEN;
+            wasm.sinewavespeechconverter_set_scala_tuning(retptr, this.__wbg_ptr, ptr0, len0, ptr1, len1, a4_frequency);
+            var r0 = getInt32Memory0()[retptr / 4 + 0];
+            var r1 = getInt32Memory0()[retptr / 4 + 1];
+            if (r1) {
+                throw takeObject(r0);
+            }
+        } finally {
+            wasm.__wbindgen_add_to_stack_pointer(16);
+        }
+    }
+    /**
+    * Either major or natural minor, `None` until the estimator has heard something.
+    * @returns {ScaleMode | undefined}
+    */
+    estimated_key_mode() {
+        const ret = wasm.sinewavespeechconverter_estimated_key_mode(this.__wbg_ptr);
+        return ret === 13 ? undefined : ret;
+    }
+    /**
+    * `None` until the estimator has heard something.
+    * @returns {NoteName | undefined}
+    */
+    estimated_key_root() {
+        const ret = wasm.sinewavespeechconverter_estimated_key_root(this.__wbg_ptr);
+        return ret === 12 ? undefined : ret;
+    }
+    /**
+    * The mapping from input to output frequency of `quantize_frequencies()` with the current
+    * key and tuning, for plotting. Returns `n_points` input frequencies in Hz, evenly spaced
+    * in cents between `min_hz` and `max_hz`, followed by the corresponding outputs in Hz.
+    * @param {number} min_hz
+    * @param {number} max_hz
+    * @param {number} n_points
+    * @param {FrequencyQuantizationType | undefined} [quantization_type]
+    * @returns {Float32Array}
+    */
+    quantization_curve(min_hz, max_hz, n_points, quantization_type) {
+        try {
+            const retptr = wasm.__wbindgen_add_to_stack_pointer(-16);
+            wasm.sinewavespeechconverter_quantization_curve(retptr, this.__wbg_ptr, min_hz, max_hz, n_points, isLikeNone(quantization_type) ? 3 : quantization_type);
+            var r0 = getInt32Memory0()[retptr / 4 + 0];
+            var r1 = getInt32Memory0()[retptr / 4 + 1];
+            var v1 = getArrayF32FromWasm0(r0, r1).slice();
+            wasm.__wbindgen_free(r0, r1 * 4, 4);
+            return v1;
+        } finally {
+            wasm.__wbindgen_add_to_stack_pointer(16);
+        }
+    }
+    /**
+    * Choose which poles become waves, which matters when `lpc_order` (see
+    * `set_analysis_config()`) is higher than twice the number of waves. Only poles between
+    * `min_hz` and `max_hz` with a bandwidth of at most `max_bandwidth_hz` are considered.
+    * @param {PoleSelection} selection
+    * @param {number} min_hz
+    * @param {number} max_hz
+    * @param {number | undefined} [max_bandwidth_hz]
+    */
+    set_pole_selection(selection, min_hz, max_hz, max_bandwidth_hz) {
+        wasm.sinewavespeechconverter_set_pole_selection(this.__wbg_ptr, selection, min_hz, max_hz, !isLikeNone(max_bandwidth_hz), isLikeNone(max_bandwidth_hz) ? 0 : max_bandwidth_hz);
+    }
+    /**
+    * Shift formants, meant to be applied between analysis and synthesis.
+    * `scale` multiplies all frequencies (vocal tract length change, e.g. ~1.4 for adult->child),
+    * `warping_curve` is a flattened list of (input Hz, output Hz) pairs
+    * and `offsets` contains one offset per wave.
     * @param {Float32Array} frequencies
-    * @param {Float32Array} magnitudes
-    * @param {Float32Array} first_phases
+    * @param {number} scale
+    * @param {Float32Array} warping_curve
+    * @param {Float32Array} offsets
+    * @param {FormantOffsetUnit} offset_unit
     * @returns {Float32Array}
     */
-    synthesize(frequencies, magnitudes, first_phases) {
+    transform_formants(frequencies, scale, warping_curve, offsets, offset_unit) {
         try {
             const retptr = wasm.__wbindgen_add_to_stack_pointer(-16);
             const ptr0 = passArrayF32ToWasm0(frequencies, wasm.__wbindgen_malloc);
             const len0 = WASM_VECTOR_LEN;
-            const ptr1 = passArrayF32ToWasm0(magnitudes, wasm.__wbindgen_malloc);
+            const ptr1 = passArrayF32ToWasm0(warping_curve, wasm.__wbindgen_malloc);
             const len1 = WASM_VECTOR_LEN;
-            const ptr2 = passArrayF32ToWasm0(first_phases, wasm.__wbindgen_malloc);
+            const ptr2 = passArrayF32ToWasm0(offsets, wasm.__wbindgen_malloc);
             const len2 = WASM_VECTOR_LEN;
-            wasm.sinewavespeechconverter_synthesize(retptr, this.__wbg_ptr, ptr0, len0, ptr1, len1, ptr2, len2);
+            wasm.sinewavespeechconverter_transform_formants(retptr, this.__wbg_ptr, ptr0, len0, scale, ptr1, len1, ptr2, len2, offset_unit);
             var r0 = getInt32Memory0()[retptr / 4 + 0];
             var r1 = getInt32Memory0()[retptr / 4 + 1];
             var v4 = getArrayF32FromWasm0(r0, r1).slice();
@@ -324,6 +874,478 @@ export class SineWaveSpeechConverter {
             wasm.__wbindgen_add_to_stack_pointer(16);
         }
     }
+    /**
+    * Pass in raw MIDI bytes, e.g. from a Web MIDI `midimessage` event.
+    * Note-on and note-off messages update the notes used by
+    * `quantize_frequencies_to_held_notes()`, everything else is ignored.
+    * @param {Uint8Array} bytes
+    */
+    handle_midi_message(bytes) {
+        const ptr0 = passArray8ToWasm0(bytes, wasm.__wbindgen_malloc);
+        const len0 = WASM_VECTOR_LEN;
+        wasm.sinewavespeechconverter_handle_midi_message(this.__wbg_ptr, ptr0, len0);
+    }
+    /**
+    * Configure how the audio is analyzed. `window_size` defaults to twice the hop size and
+    * must be at least the hop size. `pre_emphasis` is 0.9 by default, 0 turns it off.
+    * `lpc_order` defaults to twice the number of waves. A higher order finds more
+    * poles than there are waves and only some of them are kept.
+    * @param {WindowType} window_type
+    * @param {number | undefined} window_size
+    * @param {number} pre_emphasis
+    * @param {number | undefined} [lpc_order]
+    */
+    set_analysis_config(window_type, window_size, pre_emphasis, lpc_order) {
+        wasm.sinewavespeechconverter_set_analysis_config(this.__wbg_ptr, window_type, !isLikeNone(window_size), isLikeNone(window_size) ? 0 : window_size, pre_emphasis, !isLikeNone(lpc_order), isLikeNone(lpc_order) ? 0 : lpc_order);
+    }
+    /**
+    * Constrain each wave to a frequency band, e.g. [200, 1000, 600, 3000, 1500, 4000]
+    * for F1, F2 and F3. `bands_hz` is a flattened list of (low, high) pairs with
+    * 0 < low < high, one per wave.
+    * Each wave takes the best pole in its band (see `set_pole_selection()`) and is
+    * silent if there is none. Waves without a band take the best remaining poles.
+    * @param {Float32Array} bands_hz
+    */
+    set_frequency_bands(bands_hz) {
+        const ptr0 = passArrayF32ToWasm0(bands_hz, wasm.__wbindgen_malloc);
+        const len0 = WASM_VECTOR_LEN;
+        wasm.sinewavespeechconverter_set_frequency_bands(this.__wbg_ptr, ptr0, len0);
+    }
+    /**
+    * Configure `retune()`. A wave only switches to a new note once the unquantized frequency
+    * is `hysteresis_cents` past the boundary between the old note and the new one, and then
+    * glides to the new note over `retune_seconds`. Zero for both gives plain quantization.
+    * @param {number} hysteresis_cents
+    * @param {number} retune_seconds
+    */
+    set_retune_settings(hysteresis_cents, retune_seconds) {
+        wasm.sinewavespeechconverter_set_retune_settings(this.__wbg_ptr, hysteresis_cents, retune_seconds);
+    }
+    /**
+    * @param {Float32Array} frequencies
+    * @param {FrequencyQuantizationType | undefined} [quantization_type]
+    * @returns {Float32Array}
+    */
+    quantize_frequencies(frequencies, quantization_type) {
+        try {
+            const retptr = wasm.__wbindgen_add_to_stack_pointer(-16);
+            const ptr0 = passArrayF32ToWasm0(frequencies, wasm.__wbindgen_malloc);
+            const len0 = WASM_VECTOR_LEN;
+            wasm.sinewavespeechconverter_quantize_frequencies(retptr, this.__wbg_ptr, ptr0, len0, isLikeNone(quantization_type) ? 3 : quantization_type);
+            var r0 = getInt32Memory0()[retptr / 4 + 0];
+            var r1 = getInt32Memory0()[retptr / 4 + 1];
+            var v2 = getArrayF32FromWasm0(r0, r1).slice();
+            wasm.__wbindgen_free(r0, r1 * 4, 4);
+            return v2;
+        } finally {
+            wasm.__wbindgen_add_to_stack_pointer(16);
+        }
+    }
+    /**
+    * Turn each wave into a track that follows the nearest pole (at most
+    * `max_deviation_cents` away) and fades out and in when its pole disappears or
+    * appears, instead of gliding, see `tracks::TrackContinuity`. None turns it off.
+    * The waves are then no longer sorted by frequency.
+    * @param {number | undefined} [max_deviation_cents]
+    */
+    set_track_continuity(max_deviation_cents) {
+        wasm.sinewavespeechconverter_set_track_continuity(this.__wbg_ptr, !isLikeNone(max_deviation_cents), isLikeNone(max_deviation_cents) ? 0 : max_deviation_cents);
+    }
+    /**
+    * Configure the cepstral analyzer. The F0 estimate of each frame is searched between
+    * `min_f0_hz` and `max_f0_hz` and sets the lifter cutoff just below the pitch period,
+    * unless `lifter_quefrency_ms` sets the cutoff explicitly.
+    * @param {number} min_f0_hz
+    * @param {number} max_f0_hz
+    * @param {number | undefined} [lifter_quefrency_ms]
+    */
+    set_cepstral_analysis(min_f0_hz, max_f0_hz, lifter_quefrency_ms) {
+        wasm.sinewavespeechconverter_set_cepstral_analysis(this.__wbg_ptr, min_f0_hz, max_f0_hz, !isLikeNone(lifter_quefrency_ms), isLikeNone(lifter_quefrency_ms) ? 0 : lifter_quefrency_ms);
+    }
+    /**
+    * Analyze on a warped frequency axis, which gives low frequencies more resolution.
+    * `warping` is the allpass warping factor between -1 and 1, 0 means no warping.
+    * The frequencies returned by `get_frequencies_and_magnitudes()` are unwarped.
+    * `lpc_vocoder()` always uses unwarped analysis.
+    * @param {number} warping
+    */
+    set_frequency_warping(warping) {
+        wasm.sinewavespeechconverter_set_frequency_warping(this.__wbg_ptr, warping);
+    }
+    /**
+    * Fit LPC on windows of two pitch periods centred on the glottal epochs instead of on
+    * fixed hops, then resample the formant tracks onto the hops. With high voices, a
+    * fixed window spans several periods and the poles get pulled toward the harmonics.
+    * The pitch is estimated as in `set_cepstral_analysis()`. Only affects LPC analysis.
+    * Epochs aren't carried over between calls, so this works best on whole recordings.
+    * @param {boolean} pitch_synchronous
+    */
+    set_pitch_synchronous(pitch_synchronous) {
+        wasm.sinewavespeechconverter_set_pitch_synchronous(this.__wbg_ptr, pitch_synchronous);
+    }
+    /**
+    * Choose how the magnitudes of the waves are estimated, see `MagnitudeEstimator`.
+    * Only affects `Analyzer::Lpc`, the other analyzers measure the magnitudes directly.
+    * @param {MagnitudeEstimator} estimator
+    */
+    set_magnitude_estimator(estimator) {
+        wasm.sinewavespeechconverter_set_magnitude_estimator(this.__wbg_ptr, estimator);
+    }
+    /**
+    * Configure the sinusoidal analyzer: a track continues to the nearest peak at most
+    * `max_deviation_cents` away. Peaks more than `threshold_db` below the strongest
+    * one of the frame are ignored.
+    * @param {number} max_deviation_cents
+    * @param {number} threshold_db
+    */
+    set_sinusoidal_analysis(max_deviation_cents, threshold_db) {
+        wasm.sinewavespeechconverter_set_sinusoidal_analysis(this.__wbg_ptr, max_deviation_cents, threshold_db);
+    }
+    /**
+    * Remove all targets of the morph used by `quantize_frequencies_morph()`,
+    * to be followed by `add_quantization_morph_target()` calls.
+    */
+    clear_quantization_morph() {
+        wasm.sinewavespeechconverter_clear_quantization_morph(this.__wbg_ptr);
+    }
+    /**
+    * Correlation between the heard pitch classes and the key profile, between -1 and 1.
+    * @returns {number}
+    */
+    estimated_key_confidence() {
+        const ret = wasm.sinewavespeechconverter_estimated_key_confidence(this.__wbg_ptr);
+        return ret;
+    }
+    /**
+    * Divide the octave into `divisions` equal steps. Since this is not a 12-note tuning,
+    * quantization ignores the key and snaps to all steps.
+    * @param {number} divisions
+    * @param {NoteName} tonic
+    * @param {number} a4_frequency
+    */
+    set_equal_division_tuning(divisions, tonic, a4_frequency) {
+        wasm.sinewavespeechconverter_set_equal_division_tuning(this.__wbg_ptr, divisions, tonic, a4_frequency);
+    }
+    /**
+    * How long (roughly) the key estimator remembers what it heard.
+    * @param {number} seconds
+    */
+    set_key_estimation_memory(seconds) {
+        wasm.sinewavespeechconverter_set_key_estimation_memory(this.__wbg_ptr, seconds);
+    }
+    /**
+    * Set the scale/chord timeline used by `quantize_frequencies_timeline()`.
+    * See `timeline::Timeline` for the JSON format.
+    * @param {string} json
+    */
+    set_quantization_timeline(json) {
+        try {
+            const retptr = wasm.__wbindgen_add_to_stack_pointer(-16);
+            const ptr0 = passStringToWasm0(json, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+            const len0 = WASM_VECTOR_LEN;
+            wasm.sinewavespeechconverter_set_quantization_timeline(retptr, this.__wbg_ptr, ptr0, len0);
+            var r0 = getInt32Memory0()[retptr / 4 + 0];
+            var r1 = getInt32Memory0()[retptr / 4 + 1];
+            if (r1) {
+                throw takeObject(r0);
+            }
+        } finally {
+            wasm.__wbindgen_add_to_stack_pointer(16);
+        }
+    }
+    /**
+    * Morph between the quantization targets. The quantized versions are averaged in the
+    * cents domain, weighted by the targets' weight curves at `position`.
+    * @param {Float32Array} frequencies
+    * @param {number} position
+    * @returns {Float32Array}
+    */
+    quantize_frequencies_morph(frequencies, position) {
+        try {
+            const retptr = wasm.__wbindgen_add_to_stack_pointer(-16);
+            const ptr0 = passArrayF32ToWasm0(frequencies, wasm.__wbindgen_malloc);
+            const len0 = WASM_VECTOR_LEN;
+            wasm.sinewavespeechconverter_quantize_frequencies_morph(retptr, this.__wbg_ptr, ptr0, len0, position);
+            var r0 = getInt32Memory0()[retptr / 4 + 0];
+            var r1 = getInt32Memory0()[retptr / 4 + 1];
+            var v2 = getArrayF32FromWasm0(r0, r1).slice();
+            wasm.__wbindgen_free(r0, r1 * 4, 4);
+            return v2;
+        } finally {
+            wasm.__wbindgen_add_to_stack_pointer(16);
+        }
+    }
+    /**
+    * Like `set_frequency_warping()` with the warping that approximates the Bark scale
+    * at the converter's sample rate.
+    */
+    set_bark_frequency_warping() {
+        wasm.sinewavespeechconverter_set_bark_frequency_warping(this.__wbg_ptr);
+    }
+    /**
+    */
+    clear_quantization_timeline() {
+        wasm.sinewavespeechconverter_clear_quantization_timeline(this.__wbg_ptr);
+    }
+    /**
+    * Add a target to the morph used by `quantize_frequencies_morph()`. `None` means
+    * unquantized. `weight_curve` is a flattened list of (morph position, weight) pairs.
+    * If it's empty, the target peaks at position i for the i-th target and crossfades
+    * linearly with its neighbors.
+    * @param {FrequencyQuantizationType | undefined} quantization_type
+    * @param {Float32Array} weight_curve
+    */
+    add_quantization_morph_target(quantization_type, weight_curve) {
+        const ptr0 = passArrayF32ToWasm0(weight_curve, wasm.__wbindgen_malloc);
+        const len0 = WASM_VECTOR_LEN;
+        wasm.sinewavespeechconverter_add_quantization_morph_target(this.__wbg_ptr, isLikeNone(quantization_type) ? 3 : quantization_type, ptr0, len0);
+    }
+    /**
+    * Like `quantization_curve()`, but for `quantize_frequencies_continuous()`.
+    * @param {number} min_hz
+    * @param {number} max_hz
+    * @param {number} n_points
+    * @param {number} quantization_strength
+    * @returns {Float32Array}
+    */
+    quantization_curve_continuous(min_hz, max_hz, n_points, quantization_strength) {
+        try {
+            const retptr = wasm.__wbindgen_add_to_stack_pointer(-16);
+            wasm.sinewavespeechconverter_quantization_curve_continuous(retptr, this.__wbg_ptr, min_hz, max_hz, n_points, quantization_strength);
+            var r0 = getInt32Memory0()[retptr / 4 + 0];
+            var r1 = getInt32Memory0()[retptr / 4 + 1];
+            var v1 = getArrayF32FromWasm0(r0, r1).slice();
+            wasm.__wbindgen_free(r0, r1 * 4, 4);
+            return v1;
+        } finally {
+            wasm.__wbindgen_add_to_stack_pointer(16);
+        }
+    }
+    /**
+    * Like `quantize_frequencies()`, but each frame uses the key that the timeline
+    * has at that point of the output. `start_seconds` is the output time of the first
+    * frame, so that a file can be rendered in chunks. Before the first change of the
+    * timeline (or without a timeline), the current key is used.
+    * @param {Float32Array} frequencies
+    * @param {FrequencyQuantizationType | undefined} quantization_type
+    * @param {number} start_seconds
+    * @returns {Float32Array}
+    */
+    quantize_frequencies_timeline(frequencies, quantization_type, start_seconds) {
+        try {
+            const retptr = wasm.__wbindgen_add_to_stack_pointer(-16);
+            const ptr0 = passArrayF32ToWasm0(frequencies, wasm.__wbindgen_malloc);
+            const len0 = WASM_VECTOR_LEN;
+            wasm.sinewavespeechconverter_quantize_frequencies_timeline(retptr, this.__wbg_ptr, ptr0, len0, isLikeNone(quantization_type) ? 3 : quantization_type, start_seconds);
+            var r0 = getInt32Memory0()[retptr / 4 + 0];
+            var r1 = getInt32Memory0()[retptr / 4 + 1];
+            var v2 = getArrayF32FromWasm0(r0, r1).slice();
+            wasm.__wbindgen_free(r0, r1 * 4, 4);
+            return v2;
+        } finally {
+            wasm.__wbindgen_add_to_stack_pointer(16);
+        }
+    }
+    /**
+    * @param {Float32Array} audio_samples
+    * @returns {Float32Array}
+    */
+    get_frequencies_and_magnitudes(audio_samples) {
+        try {
+            const retptr = wasm.__wbindgen_add_to_stack_pointer(-16);
+            const ptr0 = passArrayF32ToWasm0(audio_samples, wasm.__wbindgen_malloc);
+            const len0 = WASM_VECTOR_LEN;
+            wasm.sinewavespeechconverter_get_frequencies_and_magnitudes(retptr, this.__wbg_ptr, ptr0, len0);
+            var r0 = getInt32Memory0()[retptr / 4 + 0];
+            var r1 = getInt32Memory0()[retptr / 4 + 1];
+            var v2 = getArrayF32FromWasm0(r0, r1).slice();
+            wasm.__wbindgen_free(r0, r1 * 4, 4);
+            return v2;
+        } finally {
+            wasm.__wbindgen_add_to_stack_pointer(16);
+        }
+    }
+    /**
+    * Feed input audio to the key estimator. Unless `key_locked` is set,
+    * the estimated key is then used for quantization.
+    * @param {Float32Array} audio_samples
+    */
+    update_key_estimate_from_audio(audio_samples) {
+        const ptr0 = passArrayF32ToWasm0(audio_samples, wasm.__wbindgen_malloc);
+        const len0 = WASM_VECTOR_LEN;
+        wasm.sinewavespeechconverter_update_key_estimate_from_audio(this.__wbg_ptr, ptr0, len0);
+    }
+    /**
+    * The original quantization slider, a preset of `quantize_frequencies_morph()`:
+    * 0 is unquantized, 1 chromatic, 2 diatonic and 3 pentatonic.
+    * @param {Float32Array} frequencies
+    * @param {number} quantization_strength
+    * @returns {Float32Array}
+    */
+    quantize_frequencies_continuous(frequencies, quantization_strength) {
+        try {
+            const retptr = wasm.__wbindgen_add_to_stack_pointer(-16);
+            const ptr0 = passArrayF32ToWasm0(frequencies, wasm.__wbindgen_malloc);
+            const len0 = WASM_VECTOR_LEN;
+            wasm.sinewavespeechconverter_quantize_frequencies_continuous(retptr, this.__wbg_ptr, ptr0, len0, quantization_strength);
+            var r0 = getInt32Memory0()[retptr / 4 + 0];
+            var r1 = getInt32Memory0()[retptr / 4 + 1];
+            var v2 = getArrayF32FromWasm0(r0, r1).slice();
+            wasm.__wbindgen_free(r0, r1 * 4, 4);
+            return v2;
+        } finally {
+            wasm.__wbindgen_add_to_stack_pointer(16);
+        }
+    }
+    /**
+    * Like `quantize_frequencies()`, but no two waves of a frame get the same note,
+    * so n waves always give an n-note chord. The waves keep their order and the total
+    * deviation in cents is minimized. With a positive `voice_leading_weight`, moving away
+    * from the previous frame's notes is penalized (per cent, relative to the deviation).
+    * @param {Float32Array} frequencies
+    * @param {FrequencyQuantizationType} quantization_type
+    * @param {number} voice_leading_weight
+    * @returns {Float32Array}
+    */
+    quantize_frequencies_polyphonic(frequencies, quantization_type, voice_leading_weight) {
+        try {
+            const retptr = wasm.__wbindgen_add_to_stack_pointer(-16);
+            const ptr0 = passArrayF32ToWasm0(frequencies, wasm.__wbindgen_malloc);
+            const len0 = WASM_VECTOR_LEN;
+            wasm.sinewavespeechconverter_quantize_frequencies_polyphonic(retptr, this.__wbg_ptr, ptr0, len0, quantization_type, voice_leading_weight);
+            var r0 = getInt32Memory0()[retptr / 4 + 0];
+            var r1 = getInt32Memory0()[retptr / 4 + 1];
+            var v2 = getArrayF32FromWasm0(r0, r1).slice();
+            wasm.__wbindgen_free(r0, r1 * 4, 4);
+            return v2;
+        } finally {
+            wasm.__wbindgen_add_to_stack_pointer(16);
+        }
+    }
+    /**
+    * Like `update_key_estimate_from_audio()`, but uses the formant tracks.
+    * @param {Float32Array} frequencies
+    * @param {Float32Array} magnitudes
+    */
+    update_key_estimate_from_tracks(frequencies, magnitudes) {
+        const ptr0 = passArrayF32ToWasm0(frequencies, wasm.__wbindgen_malloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ptr1 = passArrayF32ToWasm0(magnitudes, wasm.__wbindgen_malloc);
+        const len1 = WASM_VECTOR_LEN;
+        wasm.sinewavespeechconverter_update_key_estimate_from_tracks(this.__wbg_ptr, ptr0, len0, ptr1, len1);
+    }
+    /**
+    * Snaps each wave to the pitch classes of the currently held notes, in any octave.
+    * Holding a chord harmonizes the speech with it, like a vocoder.
+    * If no notes are held, the frequencies are returned unchanged.
+    * @param {Float32Array} frequencies
+    * @returns {Float32Array}
+    */
+    quantize_frequencies_to_held_notes(frequencies) {
+        try {
+            const retptr = wasm.__wbindgen_add_to_stack_pointer(-16);
+            const ptr0 = passArrayF32ToWasm0(frequencies, wasm.__wbindgen_malloc);
+            const len0 = WASM_VECTOR_LEN;
+            wasm.sinewavespeechconverter_quantize_frequencies_to_held_notes(retptr, this.__wbg_ptr, ptr0, len0);
+            var r0 = getInt32Memory0()[retptr / 4 + 0];
+            var r1 = getInt32Memory0()[retptr / 4 + 1];
+            var v2 = getArrayF32FromWasm0(r0, r1).slice();
+            wasm.__wbindgen_free(r0, r1 * 4, 4);
+            return v2;
+        } finally {
+            wasm.__wbindgen_add_to_stack_pointer(16);
+        }
+    }
+    /**
+    * Like `add_quantization_morph_target()`, but snaps to any scale, given as semitones
+    * above the root.
+    * @param {NoteName} root
+    * @param {Int32Array} intervals
+    * @param {Float32Array} weight_curve
+    */
+    add_quantization_morph_scale_target(root, intervals, weight_curve) {
+        const ptr0 = passArray32ToWasm0(intervals, wasm.__wbindgen_malloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ptr1 = passArrayF32ToWasm0(weight_curve, wasm.__wbindgen_malloc);
+        const len1 = WASM_VECTOR_LEN;
+        wasm.sinewavespeechconverter_add_quantization_morph_scale_target(this.__wbg_ptr, root, ptr0, len0, ptr1, len1);
+    }
+    /**
+    * @param {number} n_waves
+    * @param {number} hop_size
+    * @param {number} sample_rate
+    * @returns {SineWaveSpeechConverter}
+    */
+    static new(n_waves, hop_size, sample_rate) {
+        const ret = wasm.sinewavespeechconverter_new(n_waves, hop_size, sample_rate);
+        return SineWaveSpeechConverter.__wrap(ret);
+    }
+    /**
+    * Forget the state kept between calls, e.g. when starting a new file.
+    */
+    reset() {
+        wasm.sinewavespeechconverter_reset(this.__wbg_ptr);
+    }
+    /**
+    * Make quantization stateful, see `set_retune_settings()`. Takes the frequencies before
+    * and after any of the quantization methods, so it works with all of them.
+    * `quantization_type` is the type used with the current key, so that a note that is no
+    * longer in the key isn't held. Pass None when quantizing to something else,
+    * e.g. held notes or a timeline, and notes are then held regardless.
+    * @param {Float32Array} unquantized
+    * @param {Float32Array} quantized
+    * @param {FrequencyQuantizationType | undefined} [quantization_type]
+    * @returns {Float32Array}
+    */
+    retune(unquantized, quantized, quantization_type) {
+        try {
+            const retptr = wasm.__wbindgen_add_to_stack_pointer(-16);
+            const ptr0 = passArrayF32ToWasm0(unquantized, wasm.__wbindgen_malloc);
+            const len0 = WASM_VECTOR_LEN;
+            const ptr1 = passArrayF32ToWasm0(quantized, wasm.__wbindgen_malloc);
+            const len1 = WASM_VECTOR_LEN;
+            wasm.sinewavespeechconverter_retune(retptr, this.__wbg_ptr, ptr0, len0, ptr1, len1, isLikeNone(quantization_type) ? 3 : quantization_type);
+            var r0 = getInt32Memory0()[retptr / 4 + 0];
+            var r1 = getInt32Memory0()[retptr / 4 + 1];
+            var v3 = getArrayF32FromWasm0(r0, r1).slice();
+            wasm.__wbindgen_free(r0, r1 * 4, 4);
+            return v3;
+        } finally {
+            wasm.__wbindgen_add_to_stack_pointer(16);
+        }
+    }
+    /**
+    * Set the key that quantization snaps to. Diatonic quantization uses all the notes
+    * of the scale and pentatonic quantization uses a five-note subset.
+    * @param {NoteName} root
+    * @param {ScaleMode} mode
+    */
+    set_key(root, mode) {
+        wasm.sinewavespeechconverter_set_key(this.__wbg_ptr, root, mode);
+    }
+    /**
+    * Add depth by lowering the first frequencies more than the last ones.
+    * Specifically, the first frequency is lowered by `width` octaves, and the last frequency
+    * is left unchanged. The deepening of the other frequencies is a linear interpolation.
+    * @param {Float32Array} frequencies
+    * @param {number} width
+    * @returns {Float32Array}
+    */
+    add_depth(frequencies, width) {
+        try {
+            const retptr = wasm.__wbindgen_add_to_stack_pointer(-16);
+            const ptr0 = passArrayF32ToWasm0(frequencies, wasm.__wbindgen_malloc);
+            const len0 = WASM_VECTOR_LEN;
+            wasm.sinewavespeechconverter_add_depth(retptr, this.__wbg_ptr, ptr0, len0, width);
+            var r0 = getInt32Memory0()[retptr / 4 + 0];
+            var r1 = getInt32Memory0()[retptr / 4 + 1];
+            var v2 = getArrayF32FromWasm0(r0, r1).slice();
+            wasm.__wbindgen_free(r0, r1 * 4, 4);
+            return v2;
+        } finally {
+            wasm.__wbindgen_add_to_stack_pointer(16);
+        }
+    }
 }
 
 async function __wbg_load(module, imports) {
@@ -363,6 +1385,14 @@ function __wbg_get_imports() {
     imports.wbg.__wbindgen_object_drop_ref = function(arg0) {
         takeObject(arg0);
     };
+    imports.wbg.__wbg_noteanalysis_new = function(arg0) {
+        const ret = NoteAnalysis.__wrap(arg0);
+        return addHeapObject(ret);
+    };
+    imports.wbg.__wbindgen_error_new = function(arg0, arg1) {
+        const ret = new Error(getStringFromWasm0(arg0, arg1));
+        return addHeapObject(ret);
+    };
     imports.wbg.__wbg_new_abda76e883ba8a5f = function() {
         const ret = new Error();
         return addHeapObject(ret);
@@ -401,6 +1431,7 @@ function __wbg_finalize_init(instance, module) {
     __wbg_init.__wbindgen_wasm_module = module;
     cachedFloat32Memory0 = null;
     cachedInt32Memory0 = null;
+    cachedUint32Memory0 = null;
     cachedUint8Memory0 = null;
 
 
@@ -1,20 +1,82 @@
 /* tslint:disable */
 /* eslint-disable */
 export const memory: WebAssembly.Memory;
-export function __wbg_sinewavespeechconverter_free(a: number): void;
-export function __wbg_get_sinewavespeechconverter_n_waves(a: number): number;
-export function __wbg_set_sinewavespeechconverter_n_waves(a: number, b: number): void;
+export function __wbg_get_noteanalysis_cents_offset(a: number): number;
+export function __wbg_get_noteanalysis_frequency_hz(a: number): number;
+export function __wbg_get_noteanalysis_midi_note(a: number): number;
+export function __wbg_get_noteanalysis_note_name(a: number): number;
+export function __wbg_get_noteanalysis_octave(a: number): number;
+export function __wbg_get_noteanalysis_wave(a: number): number;
+export function __wbg_noteanalysis_free(a: number): void;
+export function __wbg_set_noteanalysis_cents_offset(a: number, b: number): void;
+export function __wbg_set_noteanalysis_frequency_hz(a: number, b: number): void;
+export function __wbg_set_noteanalysis_midi_note(a: number, b: number): void;
+export function __wbg_set_noteanalysis_note_name(a: number, b: number): void;
+export function __wbg_set_noteanalysis_octave(a: number, b: number): void;
+export function __wbg_set_noteanalysis_wave(a: number, b: number): void;
+export function noteanalysis_label(a: number, b: number): void;
 export function __wbg_get_sinewavespeechconverter_hop_size(a: number): number;
-export function __wbg_set_sinewavespeechconverter_hop_size(a: number, b: number): void;
+export function __wbg_get_sinewavespeechconverter_key_locked(a: number): number;
+export function __wbg_get_sinewavespeechconverter_n_waves(a: number): number;
 export function __wbg_get_sinewavespeechconverter_sample_rate(a: number): number;
+export function __wbg_get_sinewavespeechconverter_time_stretch(a: number): number;
+export function __wbg_set_sinewavespeechconverter_hop_size(a: number, b: number): void;
+export function __wbg_set_sinewavespeechconverter_key_locked(a: number, b: number): void;
+export function __wbg_set_sinewavespeechconverter_n_waves(a: number, b: number): void;
 export function __wbg_set_sinewavespeechconverter_sample_rate(a: number, b: number): void;
-export function sinewavespeechconverter_new(a: number, b: number, c: number): number;
+export function __wbg_set_sinewavespeechconverter_time_stretch(a: number, b: number): void;
+export function __wbg_sinewavespeechconverter_free(a: number): void;
+export function sinewavespeechconverter_add_depth(a: number, b: number, c: number, d: number, e: number): void;
+export function sinewavespeechconverter_add_quantization_morph_scale_target(a: number, b: number, c: number, d: number, e: number, f: number): void;
+export function sinewavespeechconverter_add_quantization_morph_target(a: number, b: number, c: number, d: number): void;
+export function sinewavespeechconverter_analyze_notes(a: number, b: number, c: number, d: number, e: number, f: number): void;
+export function sinewavespeechconverter_clear_quantization_morph(a: number): void;
+export function sinewavespeechconverter_clear_quantization_timeline(a: number): void;
+export function sinewavespeechconverter_estimated_key_confidence(a: number): number;
+export function sinewavespeechconverter_estimated_key_mode(a: number): number;
+export function sinewavespeechconverter_estimated_key_root(a: number): number;
+export function sinewavespeechconverter_export_midi(a: number, b: number, c: number, d: number, e: number, f: number, g: number, h: number): void;
+export function sinewavespeechconverter_fold_registers(a: number, b: number, c: number, d: number, e: number, f: number): void;
 export function sinewavespeechconverter_get_frequencies_and_magnitudes(a: number, b: number, c: number, d: number): void;
+export function sinewavespeechconverter_get_lpc_envelope(a: number, b: number, c: number, d: number, e: number, f: number, g: number, h: number, i: number): void;
+export function sinewavespeechconverter_get_lpc_residual(a: number, b: number, c: number, d: number): void;
+export function sinewavespeechconverter_handle_midi_message(a: number, b: number, c: number): void;
+export function sinewavespeechconverter_lpc_vocoder(a: number, b: number, c: number, d: number, e: number, f: number, g: number, h: number): void;
+export function sinewavespeechconverter_new(a: number, b: number, c: number): number;
+export function sinewavespeechconverter_quantization_curve(a: number, b: number, c: number, d: number, e: number, f: number): void;
+export function sinewavespeechconverter_quantization_curve_continuous(a: number, b: number, c: number, d: number, e: number, f: number): void;
 export function sinewavespeechconverter_quantize_frequencies(a: number, b: number, c: number, d: number, e: number): void;
 export function sinewavespeechconverter_quantize_frequencies_continuous(a: number, b: number, c: number, d: number, e: number): void;
-export function sinewavespeechconverter_add_depth(a: number, b: number, c: number, d: number, e: number): void;
+export function sinewavespeechconverter_quantize_frequencies_morph(a: number, b: number, c: number, d: number, e: number): void;
+export function sinewavespeechconverter_quantize_frequencies_polyphonic(a: number, b: number, c: number, d: number, e: number, f: number): void;
+export function sinewavespeechconverter_quantize_frequencies_timeline(a: number, b: number, c: number, d: number, e: number, f: number): void;
+export function sinewavespeechconverter_quantize_frequencies_to_held_notes(a: number, b: number, c: number, d: number): void;
+export function sinewavespeechconverter_reset(a: number): void;
+export function sinewavespeechconverter_retune(a: number, b: number, c: number, d: number, e: number, f: number, g: number): void;
+export function sinewavespeechconverter_set_analysis_config(a: number, b: number, c: number, d: number, e: number, f: number, g: number): void;
+export function sinewavespeechconverter_set_analyzer(a: number, b: number): void;
+export function sinewavespeechconverter_set_bark_frequency_warping(a: number): void;
+export function sinewavespeechconverter_set_cepstral_analysis(a: number, b: number, c: number, d: number, e: number): void;
+export function sinewavespeechconverter_set_custom_key(a: number, b: number, c: number, d: number): void;
+export function sinewavespeechconverter_set_equal_division_tuning(a: number, b: number, c: number, d: number): void;
+export function sinewavespeechconverter_set_frequency_bands(a: number, b: number, c: number): void;
+export function sinewavespeechconverter_set_frequency_warping(a: number, b: number): void;
+export function sinewavespeechconverter_set_key(a: number, b: number, c: number): void;
+export function sinewavespeechconverter_set_key_estimation_memory(a: number, b: number): void;
+export function sinewavespeechconverter_set_magnitude_estimator(a: number, b: number): void;
+export function sinewavespeechconverter_set_pitch_synchronous(a: number, b: number): void;
+export function sinewavespeechconverter_set_pole_selection(a: number, b: number, c: number, d: number, e: number, f: number): void;
+export function sinewavespeechconverter_set_quantization_timeline(a: number, b: number, c: number, d: number): void;
+export function sinewavespeechconverter_set_retune_settings(a: number, b: number, c: number): void;
+export function sinewavespeechconverter_set_scala_tuning(a: number, b: number, c: number, d: number, e: number, f: number, g: number): void;
+export function sinewavespeechconverter_set_sinusoidal_analysis(a: number, b: number, c: number): void;
+export function sinewavespeechconverter_set_track_continuity(a: number, b: number, c: number): void;
+export function sinewavespeechconverter_set_tuning(a: number, b: number, c: number, d: number): void;
 export function sinewavespeechconverter_synthesize(a: number, b: number, c: number, d: number, e: number, f: number, g: number, h: number): void;
+export function sinewavespeechconverter_transform_formants(a: number, b: number, c: number, d: number, e: number, f: number, g: number, h: number, i: number, j: number): void;
+export function sinewavespeechconverter_update_key_estimate_from_audio(a: number, b: number, c: number): void;
+export function sinewavespeechconverter_update_key_estimate_from_tracks(a: number, b: number, c: number, d: number, e: number): void;
 export function __wbindgen_add_to_stack_pointer(a: number): number;
-export function __wbindgen_malloc(a: number, b: number): number;
 export function __wbindgen_free(a: number, b: number, c: number): void;
+export function __wbindgen_malloc(a: number, b: number): number;
 export function __wbindgen_realloc(a: number, b: number, c: number, d: number): number;
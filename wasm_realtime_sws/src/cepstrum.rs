@@ -0,0 +1,190 @@
+use ndarray::prelude::*;
+use rustfft::{num_complex::Complex, FftDirection};
+
+use crate::signal_processing::{fft_in_place, magnitude_spectrum};
+use crate::sinusoidal::interpolated_peaks;
+
+/// A cepstral peak below this (in nepers) means the frame has no clear pitch.
+const VOICING_THRESHOLD: f32 = 0.05;
+
+/// When the lifter follows the F0 estimate, it cuts at this fraction of the pitch period
+/// so that the harmonics don't leak into the envelope.
+const LIFTER_FRACTION: f32 = 0.7;
+
+/// Real cepstrum of a windowed frame zero-padded to `fft_size`, a power of two: the inverse
+/// FFT of the log magnitude spectrum. Index q is the quefrency in samples.
+pub fn real_cepstrum(frame: ArrayView1<f32>, fft_size: usize) -> Array1<f32> {
+    let log_magnitude = magnitude_spectrum(frame, fft_size).mapv(|x| x.max(1e-10).ln());
+
+    // The spectrum of a real signal is symmetric, fill in the negative frequencies
+    let mut buffer: Vec<Complex<f32>> = (0..fft_size)
+        .map(|k| Complex::new(log_magnitude[k.min(fft_size - k)], 0.0))
+        .collect();
+    fft_in_place(&mut buffer, FftDirection::Inverse);
+
+    buffer.iter().map(|x| x.re / fft_size as f32).collect()
+}
+
+/// Smooth log magnitude spectrum (natural log) from the quefrencies of `cepstrum` below
+/// `lifter`. Has `fft_size / 2 + 1` bins like `magnitude_spectrum()`.
+pub fn cepstral_envelope(cepstrum: ArrayView1<f32>, lifter: usize) -> Array1<f32> {
+    let fft_size = cepstrum.len();
+    let lifter = lifter.clamp(1, fft_size / 2);
+
+    let mut buffer: Vec<Complex<f32>> = (0..fft_size)
+        .map(|q| {
+            let keep = q < lifter || q > fft_size - lifter;
+            Complex::new(if keep { cepstrum[q] } else { 0.0 }, 0.0)
+        })
+        .collect();
+    fft_in_place(&mut buffer, FftDirection::Forward);
+
+    buffer[..fft_size / 2 + 1].iter().map(|x| x.re).collect()
+}
+
+/// Estimate the pitch period in samples from the highest cepstral peak between
+/// `min_period` and `max_period`. None if the peak is too weak, i.e. the frame is unvoiced.
+pub fn estimate_period(cepstrum: ArrayView1<f32>, min_period: f32, max_period: f32) -> Option<f32> {
+    let start = (min_period.floor() as usize).max(1);
+    let end = (max_period.ceil() as usize).min(cepstrum.len() / 2 - 1);
+    let peak = (start..=end).max_by(|&a, &b| cepstrum[a].partial_cmp(&cepstrum[b]).unwrap())?;
+    if cepstrum[peak] < VOICING_THRESHOLD {
+        return None;
+    }
+
+    // Parabolic interpolation for sub-sample precision
+    let (alpha, beta, gamma) = (cepstrum[peak - 1], cepstrum[peak], cepstrum[peak + 1]);
+    let denominator = alpha - 2.0 * beta + gamma;
+    let offset = if denominator < 0.0 {
+        0.5 * (alpha - gamma) / denominator
+    } else {
+        0.0
+    };
+    Some(peak as f32 + offset)
+}
+
+/// Formant estimation from the peaks of the cepstrally smoothed spectrum. Unlike the
+/// poles of an LPC fit, the smoothed spectrum has no harmonics left for the waves to latch
+/// onto, which makes it more robust for high voices (children, sopranos).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CepstralAnalyzer {
+    /// Range of the F0 estimate, in normalized frequency.
+    pub min_f0: f32,
+    pub max_f0: f32,
+    /// Lifter cutoff quefrency in samples. If None, it follows the F0 estimate of each
+    /// frame, falling back to the period of `max_f0` for unvoiced frames.
+    pub lifter: Option<usize>,
+}
+
+impl CepstralAnalyzer {
+    /// `frames` has shape (n_frames, window_size) and is already windowed with a window
+    /// that sums to `window_sum`. Returns (frequencies, magnitudes) of shape (n_frames, n_waves):
+    /// the `n_waves` highest envelope peaks sorted by frequency, 0 for missing peaks.
+    pub fn analyze(
+        &self,
+        frames: ArrayView2<f32>,
+        window_sum: f32,
+        n_waves: usize,
+    ) -> (Array2<f32>, Array2<f32>) {
+        let fft_size = 2 * frames.ncols().next_power_of_two();
+        let mut frequencies = Array2::zeros((frames.nrows(), n_waves));
+        let mut magnitudes = Array2::zeros((frames.nrows(), n_waves));
+
+        for (i, frame) in frames.outer_iter().enumerate() {
+            let cepstrum = real_cepstrum(frame, fft_size);
+            let lifter = self.lifter.unwrap_or_else(|| {
+                let period = self.period(cepstrum.view());
+                (LIFTER_FRACTION * period).round() as usize
+            });
+            let envelope_db = cepstral_envelope(cepstrum.view(), lifter)
+                .mapv(|x| x * 20.0 / std::f32::consts::LN_10);
+
+            let mut peaks = interpolated_peaks(envelope_db.view(), f32::INFINITY);
+            peaks.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+            peaks.truncate(n_waves);
+            peaks.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+            for (wave, (bin, peak_db)) in peaks.into_iter().enumerate() {
+                frequencies[[i, wave]] = bin * 2.0 * std::f32::consts::PI / fft_size as f32;
+                // Same scale as the amplitudes of the sinusoidal analyzer
+                magnitudes[[i, wave]] = 10f32.powf(peak_db / 20.0) * 2.0 / window_sum;
+            }
+        }
+
+        (frequencies, magnitudes)
+    }
+
+    /// The pitch period of each frame in samples, None for unvoiced frames.
+    pub fn periods(&self, frames: ArrayView2<f32>) -> Vec<Option<f32>> {
+        let fft_size = 2 * frames.ncols().next_power_of_two();
+        frames
+            .outer_iter()
+            .map(|frame| self.estimate_period(real_cepstrum(frame, fft_size).view()))
+            .collect()
+    }
+
+    /// The pitch period of a frame in samples, or the shortest one if unvoiced.
+    fn period(&self, cepstrum: ArrayView1<f32>) -> f32 {
+        self.estimate_period(cepstrum)
+            .unwrap_or_else(|| to_period(self.max_f0))
+    }
+
+    fn estimate_period(&self, cepstrum: ArrayView1<f32>) -> Option<f32> {
+        estimate_period(cepstrum, to_period(self.max_f0), to_period(self.min_f0))
+    }
+}
+
+fn to_period(f0: f32) -> f32 {
+    2.0 * std::f32::consts::PI / f0
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::signal_processing::hann_window;
+
+    /// A pulse train through two resonators, like a vowel with two formants.
+    pub fn vowel(period: usize, formants: &[f32], length: usize) -> Array1<f32> {
+        let mut signal: Array1<f32> =
+            Array1::from_iter((0..length).map(|i| if i % period == 0 { 1.0 } else { 0.0 }));
+        for &formant in formants {
+            let r: f32 = 0.97;
+            let (a1, a2) = (-2.0 * r * formant.cos(), r * r);
+            let mut y = Array1::zeros(length);
+            for n in 0..length {
+                let y1 = if n >= 1 { y[n - 1] } else { 0.0 };
+                let y2 = if n >= 2 { y[n - 2] } else { 0.0 };
+                y[n] = signal[n] - a1 * y1 - a2 * y2;
+            }
+            signal = y;
+        }
+        signal
+    }
+
+    #[test]
+    fn test_estimate_period() {
+        let window = hann_window(512);
+        let frame = &vowel(40, &[0.3, 1.0], 512) * &window;
+        let cepstrum = real_cepstrum(frame.view(), 1024);
+        let period = estimate_period(cepstrum.view(), 10.0, 200.0).unwrap();
+        assert!((period - 40.0).abs() < 1.0, "period: {}", period);
+    }
+
+    #[test]
+    fn test_formants_not_harmonics() {
+        // A high voice: the harmonics are 2pi/25 = 0.25 apart
+        let window = hann_window(512);
+        let frame = &vowel(25, &[0.5, 1.5], 512) * &window;
+        let frames = frame.insert_axis(Axis(0));
+        let analyzer = CepstralAnalyzer {
+            min_f0: 0.05,
+            max_f0: 0.5,
+            lifter: None,
+        };
+
+        let (frequencies, magnitudes) = analyzer.analyze(frames.view(), window.sum(), 2);
+        assert!((frequencies[[0, 0]] - 0.5).abs() < 0.1, "{}", frequencies);
+        assert!((frequencies[[0, 1]] - 1.5).abs() < 0.1, "{}", frequencies);
+        assert!(magnitudes.iter().all(|&m| m > 0.0));
+    }
+}
@@ -0,0 +1,168 @@
+use wasm_bindgen::prelude::wasm_bindgen;
+
+#[wasm_bindgen]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum FormantOffsetUnit {
+    Hz,
+    Semitones,
+}
+
+/// Moves formant tracks around, e.g. to simulate a shorter or longer vocal tract.
+///
+/// The steps are applied in this order:
+/// 1. Multiply every frequency by `scale` (vocal tract length change).
+/// 2. Map the result through the piecewise-linear `warping_curve`.
+/// 3. Add the per-wave offset.
+///
+/// All frequencies here are in Hz, the conversion from normalized frequencies
+/// happens in `apply()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormantTransformation {
+    /// A vocal tract that is shorter by a factor of k has its formants higher by a factor of k.
+    /// Adult male to child is roughly 1.3-1.5.
+    pub scale: f32,
+    /// Points (input Hz, output Hz) sorted by input frequency. Outside the given points,
+    /// the first and last segment are extended linearly. Empty means identity.
+    pub warping_curve: Vec<(f32, f32)>,
+    /// Offset for each wave. If there are fewer offsets than waves, the remaining waves
+    /// are not offset.
+    pub offsets: Vec<f32>,
+    pub offset_unit: FormantOffsetUnit,
+}
+
+impl Default for FormantTransformation {
+    fn default() -> Self {
+        FormantTransformation {
+            scale: 1.0,
+            warping_curve: Vec::new(),
+            offsets: Vec::new(),
+            offset_unit: FormantOffsetUnit::Hz,
+        }
+    }
+}
+
+impl FormantTransformation {
+    /// Transform a single frequency in Hz belonging to the wave with index `wave_index`.
+    pub fn transform_hz(&self, frequency_hz: f32, wave_index: usize) -> f32 {
+        let scaled = frequency_hz * self.scale;
+        let warped = piecewise_linear(&self.warping_curve, scaled);
+
+        match self.offsets.get(wave_index) {
+            None => warped,
+            Some(offset) => match self.offset_unit {
+                FormantOffsetUnit::Hz => warped + offset,
+                FormantOffsetUnit::Semitones => warped * 2.0f32.powf(offset / 12.0),
+            },
+        }
+    }
+
+    /// Transform normalized frequencies (radians/sample) flattened from a
+    /// (n_steps, n_waves) array.
+    ///
+    /// Frequencies of 0 mean there is no formant, so they are left untouched.
+    /// The results are clamped to stay between 0 and the Nyquist frequency.
+    pub fn apply(&self, frequencies: &[f32], n_waves: usize, sample_rate: usize) -> Vec<f32> {
+        let to_hz = sample_rate as f32 / (2. * std::f32::consts::PI);
+        let nyquist_hz = sample_rate as f32 / 2.0;
+
+        frequencies
+            .iter()
+            .enumerate()
+            .map(|(i, &f)| {
+                if f <= 0.0 {
+                    return f;
+                }
+                let transformed = self.transform_hz(f * to_hz, i % n_waves);
+                transformed.clamp(0.0, nyquist_hz) / to_hz
+            })
+            .collect()
+    }
+}
+
+/// Evaluate a piecewise-linear function given by (x, y) points sorted by x.
+fn piecewise_linear(points: &[(f32, f32)], x: f32) -> f32 {
+    match points.len() {
+        0 => x,
+        // A single point means a constant shift
+        1 => x - points[0].0 + points[0].1,
+        _ => {
+            // Find the segment to use, the first and last one are extended to infinity
+            let segment = points
+                .windows(2)
+                .position(|w| x < w[1].0)
+                .unwrap_or(points.len() - 2);
+            let (x0, y0) = points[segment];
+            let (x1, y1) = points[segment + 1];
+
+            if x1 == x0 {
+                return y1;
+            }
+            y0 + (x - x0) * (y1 - y0) / (x1 - x0)
+        }
+    }
+}
+
+/// Parse a flattened [in_0, out_0, in_1, out_1, ...] curve as passed from JS.
+pub fn warping_curve_from_flat(flat: &[f32]) -> Vec<(f32, f32)> {
+    if flat.len() % 2 != 0 {
+        panic!(
+            "warping curve must have an even number of values, got {}",
+            flat.len()
+        );
+    }
+    let mut curve: Vec<(f32, f32)> = flat.chunks(2).map(|c| (c[0], c[1])).collect();
+    curve.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    curve
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_piecewise_linear() {
+        let points = vec![(0., 0.), (1000., 500.), (2000., 2500.)];
+
+        assert_eq!(piecewise_linear(&points, 500.), 250.);
+        assert_eq!(piecewise_linear(&points, 1500.), 1500.);
+        // Extrapolation uses the closest segment
+        assert_eq!(piecewise_linear(&points, 3000.), 4500.);
+        assert_eq!(piecewise_linear(&points, -1000.), -500.);
+
+        assert_eq!(piecewise_linear(&[], 123.), 123.);
+        assert_eq!(piecewise_linear(&[(100., 200.)], 150.), 250.);
+    }
+
+    #[test]
+    fn test_formant_transformation() {
+        let transformation = FormantTransformation {
+            scale: 2.0,
+            offsets: vec![100.0],
+            ..Default::default()
+        };
+        assert_eq!(transformation.transform_hz(500., 0), 1100.);
+        assert_eq!(transformation.transform_hz(500., 1), 1000.);
+
+        let transformation = FormantTransformation {
+            offsets: vec![0.0, 12.0],
+            offset_unit: FormantOffsetUnit::Semitones,
+            ..Default::default()
+        };
+        let sample_rate = 8000;
+        let to_normalized = (2. * std::f32::consts::PI) / sample_rate as f32;
+        let frequencies = vec![
+            500. * to_normalized,
+            1000. * to_normalized,
+            0.0,
+            3000. * to_normalized,
+        ];
+        let transformed = transformation.apply(&frequencies, 2, sample_rate);
+
+        assert_eq!(transformed[0], frequencies[0]);
+        assert!((transformed[1] / to_normalized - 2000.).abs() < 1e-2);
+        // Missing formants are left alone
+        assert_eq!(transformed[2], 0.0);
+        // Clamped to Nyquist
+        assert!((transformed[3] / to_normalized - 4000.).abs() < 1e-2);
+    }
+}
@@ -0,0 +1,199 @@
+use ndarray::{ArrayView1, ArrayView2};
+
+use crate::{
+    midi::frequency_to_midi_note,
+    music::{Key, NoteName, ScaleMode},
+    signal_processing::{hann_window, magnitude_spectrum},
+};
+
+// Krumhansl-Kessler key profiles, starting from the tonic.
+// See e.g. http://rnhart.net/articles/key-finding/
+const MAJOR_PROFILE: [f32; 12] = [
+    6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88,
+];
+const MINOR_PROFILE: [f32; 12] = [
+    6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17,
+];
+
+/// Frequencies outside of this range don't say much about the key.
+const MIN_FREQUENCY_HZ: f32 = 50.0;
+const MAX_FREQUENCY_HZ: f32 = 5000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyEstimate {
+    pub root: NoteName,
+    /// Either `Major` or `NaturalMinor`.
+    pub mode: ScaleMode,
+    /// Correlation of the chroma profile with the key profile, between -1 and 1.
+    pub confidence: f32,
+}
+
+impl KeyEstimate {
+    pub fn key(&self) -> Key {
+        Key::new(self.root, self.mode)
+    }
+}
+
+/// Estimates the key from a chroma profile (how much energy there is in each pitch class)
+/// that is accumulated over time, in the style of the Krumhansl-Schmuckler algorithm.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyEstimator {
+    chroma: [f32; 12],
+    /// Older observations are forgotten with this time constant.
+    pub memory_seconds: f32,
+}
+
+impl KeyEstimator {
+    pub fn new(memory_seconds: f32) -> KeyEstimator {
+        KeyEstimator {
+            chroma: [0.0; 12],
+            memory_seconds,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.chroma = [0.0; 12];
+    }
+
+    fn decay(&mut self, seconds: f32) {
+        if self.memory_seconds > 0.0 {
+            let decay = (-seconds / self.memory_seconds).exp();
+            self.chroma.iter_mut().for_each(|x| *x *= decay);
+        }
+    }
+
+    fn add(&mut self, frequency_hz: f32, weight: f32) {
+        if !(MIN_FREQUENCY_HZ..=MAX_FREQUENCY_HZ).contains(&frequency_hz) {
+            return;
+        }
+        let pitch_class = (frequency_to_midi_note(frequency_hz).round() as i32).rem_euclid(12);
+        self.chroma[pitch_class as usize] += weight;
+    }
+
+    /// Accumulate the spectrum of a chunk of input audio.
+    pub fn add_audio(&mut self, audio: ArrayView1<f32>, sample_rate: usize) {
+        if audio.is_empty() {
+            return;
+        }
+        self.decay(audio.len() as f32 / sample_rate as f32);
+
+        let windowed = &audio * &hann_window(audio.len());
+        let fft_size = audio.len().next_power_of_two();
+        let spectrum = magnitude_spectrum(windowed.view(), fft_size);
+        for (bin, magnitude) in spectrum.iter().enumerate() {
+            let frequency_hz = bin as f32 * sample_rate as f32 / fft_size as f32;
+            self.add(frequency_hz, magnitude.powi(2));
+        }
+    }
+
+    /// Accumulate formant tracks with shape (n_frames, n_waves).
+    pub fn add_tracks(
+        &mut self,
+        frequencies_hz: ArrayView2<f32>,
+        magnitudes: ArrayView2<f32>,
+        seconds_per_frame: f32,
+    ) {
+        assert_eq!(frequencies_hz.shape(), magnitudes.shape());
+        for (frequencies, magnitudes) in frequencies_hz.outer_iter().zip(magnitudes.outer_iter()) {
+            self.decay(seconds_per_frame);
+            for (frequency, magnitude) in frequencies.iter().zip(magnitudes.iter()) {
+                self.add(*frequency, magnitude.powi(2));
+            }
+        }
+    }
+
+    /// The best-matching major or minor key, or `None` if we haven't heard anything yet.
+    pub fn estimate(&self) -> Option<KeyEstimate> {
+        if self.chroma.iter().all(|x| *x == 0.0) {
+            return None;
+        }
+
+        let mut best: Option<KeyEstimate> = None;
+        for root in 0..12 {
+            for (mode, profile) in [
+                (ScaleMode::Major, &MAJOR_PROFILE),
+                (ScaleMode::NaturalMinor, &MINOR_PROFILE),
+            ] {
+                // Rotate the chroma so that the candidate root comes first
+                let rotated: Vec<f32> = (0..12).map(|i| self.chroma[(root + i) % 12]).collect();
+                let correlation = pearson_correlation(&rotated, profile);
+                if best.map_or(true, |b| correlation > b.confidence) {
+                    best = Some(KeyEstimate {
+                        root: NoteName::from_pitch_class(root as i32),
+                        mode,
+                        confidence: correlation,
+                    });
+                }
+            }
+        }
+        best
+    }
+}
+
+fn pearson_correlation(a: &[f32], b: &[f32]) -> f32 {
+    let n = a.len() as f32;
+    let mean_a = a.iter().sum::<f32>() / n;
+    let mean_b = b.iter().sum::<f32>() / n;
+
+    let mut covariance = 0.0;
+    let mut variance_a = 0.0;
+    let mut variance_b = 0.0;
+    for (x, y) in a.iter().zip(b) {
+        covariance += (x - mean_a) * (y - mean_b);
+        variance_a += (x - mean_a).powi(2);
+        variance_b += (y - mean_b).powi(2);
+    }
+    if variance_a == 0.0 || variance_b == 0.0 {
+        return 0.0;
+    }
+    covariance / (variance_a * variance_b).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::{Array1, Array2};
+
+    fn note_hz(midi_note: i32) -> f32 {
+        440.0 * 2.0f32.powf((midi_note - 69) as f32 / 12.0)
+    }
+
+    #[test]
+    fn test_estimate_from_tracks() {
+        let mut estimator = KeyEstimator::new(0.0);
+        assert_eq!(estimator.estimate(), None);
+
+        // An A minor arpeggio with some passing notes
+        let notes = [57, 60, 64, 69, 62, 65, 57, 64, 71, 57];
+        let frequencies = Array2::from_shape_fn((notes.len(), 1), |(i, _)| note_hz(notes[i]));
+        let magnitudes = Array2::ones((notes.len(), 1));
+        estimator.add_tracks(frequencies.view(), magnitudes.view(), 0.1);
+
+        let estimate = estimator.estimate().unwrap();
+        assert_eq!(estimate.root, NoteName::A);
+        assert_eq!(estimate.mode, ScaleMode::NaturalMinor);
+        assert!(estimate.confidence > 0.5);
+    }
+
+    #[test]
+    fn test_estimate_from_audio() {
+        let sample_rate = 8000;
+        let mut estimator = KeyEstimator::new(10.0);
+
+        // A G major triad with a strong root
+        let audio = Array1::from_iter((0..4096).map(|i| {
+            let t = i as f32 / sample_rate as f32;
+            [(67, 1.0), (71, 0.5), (74, 0.7), (55, 0.8)]
+                .iter()
+                .map(|(note, amplitude)| {
+                    amplitude * (2.0 * std::f32::consts::PI * note_hz(*note) * t).sin()
+                })
+                .sum::<f32>()
+        }));
+        estimator.add_audio(audio.view(), sample_rate);
+
+        let estimate = estimator.estimate().unwrap();
+        assert_eq!(estimate.root, NoteName::G);
+        assert_eq!(estimate.mode, ScaleMode::Major);
+    }
+}
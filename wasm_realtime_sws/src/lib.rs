@@ -1,13 +1,39 @@
-use ndarray::{Array, Array2};
+use ndarray::{Array, Array1, Array2};
 use synthesis::synthesize;
 use wasm_bindgen::prelude::*;
 
+mod cepstrum;
+mod formant_shift;
+mod key_estimation;
 mod linear_algebra;
 mod lpc;
+mod midi;
+mod morph;
 mod music;
+mod pitch_synchronous;
+mod retune;
 mod signal_processing;
+mod sinusoidal;
 mod synthesis;
+mod timeline;
+mod tracks;
+mod tuning;
 mod utils;
+mod vocoder;
+
+/// How `get_frequencies_and_magnitudes()` finds the waves.
+#[wasm_bindgen]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Analyzer {
+    /// Formants from the poles of an LPC fit, the classic sine wave speech.
+    Lpc,
+    /// Spectral peaks continued into tracks, better for music and singing.
+    /// See `sinusoidal::SinusoidalAnalyzer`.
+    Sinusoidal,
+    /// Formants from the peaks of the cepstrally smoothed spectrum, more robust than LPC
+    /// for high voices. See `cepstrum::CepstralAnalyzer`.
+    Cepstral,
+}
 
 /// Note that the converter doesn't care about the sample rate,
 #[wasm_bindgen]
@@ -15,35 +41,112 @@ pub struct SineWaveSpeechConverter {
     pub n_waves: usize,  // 4 is the default in Python
     pub hop_size: usize, // 256 is the default in Python
     pub sample_rate: usize,
+    /// Synthesis uses a hop size of `hop_size * time_stretch`, so values above 1
+    /// slow the speech down. Can be fractional.
+    pub time_stretch: f32,
+    /// If false, the key estimated by the `update_key_estimate_*()` methods
+    /// is used for quantization. Set to true to keep the current key.
+    pub key_locked: bool,
+    /// Where the next synthesize() call should start, in samples relative to its first frame.
+    /// Needed so that fractional synthesis hop sizes don't drift when processing in chunks.
+    synthesis_offset: f32,
+    /// The key used for quantization, C major by default.
+    key: music::Key,
+    /// Which frequencies the notes have, 12-TET at A4 = 440 Hz by default.
+    tuning: tuning::Tuning,
+    /// Notes held down on a MIDI keyboard, for quantizing to a chord.
+    held_notes: midi::HeldNotes,
+    /// Hysteresis and glide state for `retune()`.
+    retuner: retune::Retuner,
+    /// The notes chosen in the last frame by `quantize_frequencies_polyphonic()`,
+    /// for voice leading.
+    previous_polyphonic_notes: Vec<f32>,
+    /// Used by `quantize_frequencies_morph()`. Defaults to the same morph as
+    /// `quantize_frequencies_continuous()`.
+    quantization_morph: morph::QuantizationMorph,
+    key_estimator: key_estimation::KeyEstimator,
+    timeline: Option<timeline::Timeline>,
+    analysis_config: lpc::AnalysisConfig,
+    /// Defaults to twice the number of waves.
+    lpc_order: Option<usize>,
+    pole_selection: lpc::PoleSelectionConfig,
+    magnitude_estimator: lpc::MagnitudeEstimator,
+    analyzer: Analyzer,
+    sinusoidal_analyzer: sinusoidal::SinusoidalAnalyzer,
+    cepstral_analyzer: cepstrum::CepstralAnalyzer,
+    /// Fit LPC once per pitch period instead of once per hop, see `set_pitch_synchronous()`.
+    pitch_synchronous: bool,
+    /// Fades waves in and out when poles appear and disappear, off by default.
+    /// See `set_track_continuity()`.
+    track_continuity: Option<tracks::TrackContinuity>,
+    /// Filter state for `lpc_vocoder()`.
+    vocoder: vocoder::LpcVocoder,
 }
 
 #[wasm_bindgen]
 impl SineWaveSpeechConverter {
     pub fn new(n_waves: usize, hop_size: usize, sample_rate: usize) -> SineWaveSpeechConverter {
         utils::set_panic_hook();
+        let to_normalized = (2. * std::f32::consts::PI) / sample_rate as f32;
 
         SineWaveSpeechConverter {
             n_waves,
             hop_size,
             sample_rate,
+            time_stretch: 1.0,
+            key_locked: false,
+            synthesis_offset: 0.0,
+            key: music::Key::default(),
+            tuning: tuning::Tuning::default(),
+            held_notes: midi::HeldNotes::default(),
+            retuner: retune::Retuner::new(0.0, 0.0),
+            previous_polyphonic_notes: Vec::new(),
+            quantization_morph: morph::QuantizationMorph::strength_preset(),
+            key_estimator: key_estimation::KeyEstimator::new(10.0),
+            timeline: None,
+            analysis_config: lpc::AnalysisConfig::default(),
+            lpc_order: None,
+            pole_selection: lpc::PoleSelectionConfig::default(),
+            magnitude_estimator: lpc::MagnitudeEstimator::PoleGain,
+            analyzer: Analyzer::Lpc,
+            sinusoidal_analyzer: sinusoidal::SinusoidalAnalyzer::new(100.0, 30.0),
+            cepstral_analyzer: cepstrum::CepstralAnalyzer {
+                min_f0: 60.0 * to_normalized,
+                max_f0: 800.0 * to_normalized,
+                lifter: None,
+            },
+            pitch_synchronous: false,
+            track_continuity: None,
+            vocoder: vocoder::LpcVocoder::default(),
         }
     }
 
     pub fn get_frequencies_and_magnitudes(&mut self, audio_samples: Vec<f32>) -> Vec<f32> {
-        let (lpc_coefficients, gain, _residual) = lpc::fit_lpc(
-            &Array::from_vec(audio_samples),
-            self.n_waves * 2,
-            self.hop_size,
-            None,
-        );
-        let (frequencies, mut magnitudes) =
-            lpc::lpc_coefficients_to_frequencies(lpc_coefficients.view(), gain.view());
+        let audio = Array::from_vec(audio_samples);
+        let (mut frequencies, mut magnitudes) = match self.analyzer {
+            Analyzer::Lpc => self.lpc_frequencies_and_magnitudes(&audio),
+            Analyzer::Sinusoidal => {
+                // Pre-emphasis would distort the amplitudes of the peaks
+                let config = lpc::AnalysisConfig {
+                    pre_emphasis: 0.0,
+                    ..self.analysis_config
+                };
+                let frames = lpc::analysis_frames(&audio, self.hop_size, &config);
+                let window_sum = config.window(self.hop_size).sum();
+                self.sinusoidal_analyzer
+                    .analyze(frames.view(), window_sum, self.n_waves)
+            }
+            Analyzer::Cepstral => {
+                let frames = lpc::analysis_frames(&audio, self.hop_size, &self.analysis_config);
+                let window_sum = self.analysis_config.window(self.hop_size).sum();
+                self.cepstral_analyzer
+                    .analyze(frames.view(), window_sum, self.n_waves)
+            }
+        };
 
-        // Normalize magnitudes by the number of waves because otherwise the total magnitude
-        // increases with the number of waves. Not sure if this is the proper way to do it
-        // but it works.
-        // Note we also apply compression to the final waveform in synthesize().
-        magnitudes.mapv_inplace(|x| x / self.n_waves as f32);
+        if let Some(track_continuity) = &mut self.track_continuity {
+            track_continuity.process(frequencies.view_mut(), magnitudes.view_mut());
+        }
 
         let frequencies = frequencies.flatten();
         let magnitudes = magnitudes.flatten();
@@ -55,68 +158,642 @@ impl SineWaveSpeechConverter {
         result
     }
 
+    /// Choose between LPC, sinusoidal and cepstral analysis. The analysis config (see
+    /// `set_analysis_config()`) applies to all of them, except that sinusoidal analysis
+    /// uses no pre-emphasis and only LPC uses the order and the frequency warping.
+    /// LPC and cepstral analysis return the waves of each hop sorted by frequency (except
+    /// for waves with bands, see `set_frequency_bands()`). Sinusoidal analysis, and any
+    /// analysis with `set_track_continuity()`, keeps each track in its wave instead, so
+    /// per-wave settings like the offsets of `transform_formants()`, `fold_registers()`
+    /// and `add_depth()` follow the tracks.
+    pub fn set_analyzer(&mut self, analyzer: Analyzer) {
+        self.analyzer = analyzer;
+    }
+
+    /// Configure the sinusoidal analyzer: a track continues to the nearest peak at most
+    /// `max_deviation_cents` away. Peaks more than `threshold_db` below the strongest
+    /// one of the frame are ignored.
+    pub fn set_sinusoidal_analysis(&mut self, max_deviation_cents: f32, threshold_db: f32) {
+        self.sinusoidal_analyzer.max_deviation_cents = max_deviation_cents;
+        self.sinusoidal_analyzer.threshold_db = threshold_db;
+    }
+
+    /// Configure the cepstral analyzer. The F0 estimate of each frame is searched between
+    /// `min_f0_hz` and `max_f0_hz` and sets the lifter cutoff just below the pitch period,
+    /// unless `lifter_quefrency_ms` sets the cutoff explicitly.
+    pub fn set_cepstral_analysis(
+        &mut self,
+        min_f0_hz: f32,
+        max_f0_hz: f32,
+        lifter_quefrency_ms: Option<f32>,
+    ) {
+        let to_normalized = (2. * std::f32::consts::PI) / self.sample_rate as f32;
+        self.cepstral_analyzer = cepstrum::CepstralAnalyzer {
+            min_f0: min_f0_hz * to_normalized,
+            max_f0: max_f0_hz * to_normalized,
+            lifter: lifter_quefrency_ms
+                .map(|ms| (ms / 1000.0 * self.sample_rate as f32).round() as usize),
+        };
+    }
+
+    /// Fit LPC on windows of two pitch periods centred on the glottal epochs instead of on
+    /// fixed hops, then resample the formant tracks onto the hops. With high voices, a
+    /// fixed window spans several periods and the poles get pulled toward the harmonics.
+    /// The pitch is estimated as in `set_cepstral_analysis()`. Only affects LPC analysis.
+    /// Epochs aren't carried over between calls, so this works best on whole recordings.
+    pub fn set_pitch_synchronous(&mut self, pitch_synchronous: bool) {
+        self.pitch_synchronous = pitch_synchronous;
+    }
+
+    /// Configure how the audio is analyzed. `window_size` defaults to twice the hop size and
+    /// must be at least the hop size. `pre_emphasis` is 0.9 by default, 0 turns it off.
+    /// `lpc_order` defaults to twice the number of waves. A higher order finds more
+    /// poles than there are waves and only some of them are kept.
+    pub fn set_analysis_config(
+        &mut self,
+        window_type: signal_processing::WindowType,
+        window_size: Option<usize>,
+        pre_emphasis: f32,
+        lpc_order: Option<usize>,
+    ) {
+        let config = lpc::AnalysisConfig {
+            window_type,
+            window_size,
+            pre_emphasis,
+            warping: self.analysis_config.warping,
+        };
+        // Fail here rather than in the middle of processing
+        let window_size = config.window_size(self.hop_size);
+        if let Some(lpc_order) = lpc_order {
+            assert!(
+                0 < lpc_order && lpc_order < window_size,
+                "LPC order must be between 1 and the window size, got {}",
+                lpc_order
+            );
+        }
+        self.analysis_config = config;
+        self.lpc_order = lpc_order;
+    }
+
+    /// Analyze on a warped frequency axis, which gives low frequencies more resolution.
+    /// `warping` is the allpass warping factor between -1 and 1, 0 means no warping.
+    /// The frequencies returned by `get_frequencies_and_magnitudes()` are unwarped.
+    /// `lpc_vocoder()` always uses unwarped analysis.
+    pub fn set_frequency_warping(&mut self, warping: f32) {
+        assert!(
+            warping.abs() < 1.0,
+            "warping must be between -1 and 1, got {}",
+            warping
+        );
+        self.analysis_config.warping = warping;
+    }
+
+    /// Like `set_frequency_warping()` with the warping that approximates the Bark scale
+    /// at the converter's sample rate.
+    pub fn set_bark_frequency_warping(&mut self) {
+        self.analysis_config.warping = lpc::bark_warping(self.sample_rate);
+    }
+
+    /// Choose which poles become waves, which matters when `lpc_order` (see
+    /// `set_analysis_config()`) is higher than twice the number of waves. Only poles between
+    /// `min_hz` and `max_hz` with a bandwidth of at most `max_bandwidth_hz` are considered.
+    pub fn set_pole_selection(
+        &mut self,
+        selection: lpc::PoleSelection,
+        min_hz: f32,
+        max_hz: f32,
+        max_bandwidth_hz: Option<f32>,
+    ) {
+        let to_normalized = (2. * std::f32::consts::PI) / self.sample_rate as f32;
+        self.pole_selection = lpc::PoleSelectionConfig {
+            selection,
+            min_frequency: min_hz * to_normalized,
+            max_frequency: max_hz * to_normalized,
+            max_bandwidth: max_bandwidth_hz.map_or(f32::INFINITY, |b| b * to_normalized),
+            bands: std::mem::take(&mut self.pole_selection.bands),
+        };
+    }
+
+    /// Choose how the magnitudes of the waves are estimated, see `MagnitudeEstimator`.
+    /// Only affects `Analyzer::Lpc`, the other analyzers measure the magnitudes directly.
+    pub fn set_magnitude_estimator(&mut self, estimator: lpc::MagnitudeEstimator) {
+        self.magnitude_estimator = estimator;
+    }
+
+    /// Turn each wave into a track that follows the nearest pole (at most
+    /// `max_deviation_cents` away) and fades out and in when its pole disappears or
+    /// appears, instead of gliding, see `tracks::TrackContinuity`. None turns it off.
+    /// The waves are then no longer sorted by frequency.
+    pub fn set_track_continuity(&mut self, max_deviation_cents: Option<f32>) {
+        self.track_continuity = max_deviation_cents.map(tracks::TrackContinuity::new);
+    }
+
+    /// Constrain each wave to a frequency band, e.g. [200, 1000, 600, 3000, 1500, 4000]
+    /// for F1, F2 and F3. `bands_hz` is a flattened list of (low, high) pairs with
+    /// 0 < low < high, one per wave.
+    /// Each wave takes the best pole in its band (see `set_pole_selection()`) and is
+    /// silent if there is none. Waves without a band take the best remaining poles.
+    pub fn set_frequency_bands(&mut self, bands_hz: Vec<f32>) {
+        let to_normalized = (2. * std::f32::consts::PI) / self.sample_rate as f32;
+        self.pole_selection.bands = music::ranges_from_flat(&bands_hz)
+            .into_iter()
+            .map(|(low, high)| (low * to_normalized, high * to_normalized))
+            .collect();
+    }
+
+    /// The spectral envelope that the LPC fit models for the last frame of the audio, for
+    /// visualization. The envelope is evaluated at `n_points` frequencies between `min_hz`
+    /// and `max_hz`, spaced evenly in Hz or, if `log_frequency_grid` is set, in cents.
+    ///
+    /// Returns the frequencies in Hz followed by the envelope. If `include_spectrum` is set,
+    /// the magnitude spectrum of the analyzed (windowed and pre-emphasized) audio follows,
+    /// scaled to be comparable with the envelope.
+    pub fn get_lpc_envelope(
+        &mut self,
+        audio_samples: Vec<f32>,
+        min_hz: f32,
+        max_hz: f32,
+        n_points: usize,
+        log_frequency_grid: bool,
+        include_spectrum: bool,
+    ) -> Vec<f32> {
+        let audio = Array::from_vec(audio_samples);
+        let (lpc_coefficients, gain, _residual) = self.fit_lpc(&audio);
+        let last_hop = gain.len() - 1;
+
+        let frequencies_hz = if log_frequency_grid {
+            music::log_spaced_frequencies(min_hz, max_hz, n_points)
+        } else {
+            let step = (max_hz - min_hz) / (n_points.max(2) - 1) as f32;
+            (0..n_points).map(|i| min_hz + i as f32 * step).collect()
+        };
+        let to_normalized = (2. * std::f32::consts::PI) / self.sample_rate as f32;
+        let frequencies: Vec<f32> = frequencies_hz.iter().map(|f| f * to_normalized).collect();
+
+        let mut result = frequencies_hz;
+        result.extend(lpc::lpc_envelope(
+            lpc_coefficients.row(last_hop),
+            gain[last_hop],
+            &frequencies,
+            self.analysis_config.warping,
+        ));
+
+        if include_spectrum {
+            let frame = lpc::last_analysis_frame(&audio, self.hop_size, &self.analysis_config);
+            // The gain is the RMS of the residual, so |X|^2 ~ window_size * gain^2 / |A|^2
+            let scale = 1.0 / (frame.len() as f32).sqrt();
+            result.extend(
+                frequencies
+                    .iter()
+                    .map(|w| signal_processing::dtft_magnitude(frame.view(), *w) * scale),
+            );
+        }
+        result
+    }
+
+    /// The residual of the LPC fit done by `get_frequencies_and_magnitudes()`, i.e. what's
+    /// left of the audio once the formants are removed. Normalized to unit RMS per frame.
+    pub fn get_lpc_residual(&mut self, audio_samples: Vec<f32>) -> Vec<f32> {
+        let (_lpc_coefficients, _gain, residual) = self.fit_lpc(&Array::from_vec(audio_samples));
+        residual.to_vec()
+    }
+
+    /// Instead of sine waves, resynthesize the audio with a full LPC vocoder: the same
+    /// analysis as `get_frequencies_and_magnitudes()` gives an all-pole filter per frame,
+    /// which is driven by `excitation`. `pulse_frequency_hz` is only used by the pulse train
+    /// (0 or less means unvoiced, i.e. noise) and `carrier` (same length as the audio,
+    /// zero-padded if shorter) only by the carrier excitation.
+    /// Returns one output sample per input sample, up to a multiple of `hop_size`.
+    pub fn lpc_vocoder(
+        &mut self,
+        audio_samples: Vec<f32>,
+        excitation: vocoder::Excitation,
+        pulse_frequency_hz: f32,
+        carrier: Option<Vec<f32>>,
+    ) -> Result<Vec<f32>, JsError> {
+        // The lattice filter runs on the linear frequency axis, so no warping here
+        let config = lpc::AnalysisConfig {
+            warping: 0.0,
+            ..self.analysis_config
+        };
+        let (lpc_coefficients, gain, residual) = lpc::fit_lpc(
+            &Array::from_vec(audio_samples),
+            self.lpc_order(),
+            self.hop_size,
+            &config,
+        );
+        let n_samples = gain.len() * self.hop_size;
+
+        let excitation = match excitation {
+            vocoder::Excitation::Residual => residual,
+            vocoder::Excitation::PulseTrain => {
+                self.vocoder
+                    .pulse_train(n_samples, pulse_frequency_hz, self.sample_rate)
+            }
+            vocoder::Excitation::Noise => self.vocoder.noise(n_samples),
+            vocoder::Excitation::Carrier => {
+                vocoder::carrier_excitation(carrier.as_deref(), n_samples, self.hop_size)?
+            }
+        };
+
+        Ok(self
+            .vocoder
+            .process(
+                lpc_coefficients.view(),
+                gain.view(),
+                excitation.view(),
+                self.hop_size,
+                &self.analysis_config,
+            )
+            .to_vec())
+    }
+
+    /// Forget the state kept between calls, e.g. when starting a new file.
+    pub fn reset(&mut self) {
+        self.synthesis_offset = 0.0;
+        self.held_notes.clear();
+        self.retuner.reset();
+        self.previous_polyphonic_notes.clear();
+        self.key_estimator.reset();
+        self.vocoder.reset();
+        if let Some(track_continuity) = &mut self.track_continuity {
+            track_continuity.reset();
+        }
+        self.sinusoidal_analyzer.reset();
+    }
+
+    /// Set the key that quantization snaps to. Diatonic quantization uses all the notes
+    /// of the scale and pentatonic quantization uses a five-note subset.
+    pub fn set_key(&mut self, root: music::NoteName, mode: music::ScaleMode) {
+        self.key = music::Key::new(root, mode);
+    }
+
+    /// Like `set_key()`, but with a user-supplied scale given as semitones above the root.
+    pub fn set_custom_key(&mut self, root: music::NoteName, intervals: Vec<i32>) {
+        self.key = music::Key::custom(root, &intervals);
+    }
+
+    /// Feed input audio to the key estimator. Unless `key_locked` is set,
+    /// the estimated key is then used for quantization.
+    pub fn update_key_estimate_from_audio(&mut self, audio_samples: Vec<f32>) {
+        self.key_estimator
+            .add_audio(Array::from_vec(audio_samples).view(), self.sample_rate);
+        self.apply_key_estimate();
+    }
+
+    /// Like `update_key_estimate_from_audio()`, but uses the formant tracks.
+    pub fn update_key_estimate_from_tracks(&mut self, frequencies: Vec<f32>, magnitudes: Vec<f32>) {
+        assert_eq!(frequencies.len(), magnitudes.len());
+        let n_steps: usize = frequencies.len() / self.n_waves;
+        let to_hz = self.sample_rate as f32 / (2. * std::f32::consts::PI);
+
+        let frequencies_hz = Array2::from_shape_vec((n_steps, self.n_waves), frequencies)
+            .unwrap()
+            .mapv(|f| f * to_hz);
+        let magnitudes = Array2::from_shape_vec((n_steps, self.n_waves), magnitudes).unwrap();
+        let seconds_per_frame = self.hop_size as f32 / self.sample_rate as f32;
+
+        self.key_estimator
+            .add_tracks(frequencies_hz.view(), magnitudes.view(), seconds_per_frame);
+        self.apply_key_estimate();
+    }
+
+    /// How long (roughly) the key estimator remembers what it heard.
+    pub fn set_key_estimation_memory(&mut self, seconds: f32) {
+        self.key_estimator.memory_seconds = seconds;
+    }
+
+    /// `None` until the estimator has heard something.
+    pub fn estimated_key_root(&self) -> Option<music::NoteName> {
+        self.key_estimator.estimate().map(|estimate| estimate.root)
+    }
+
+    /// Either major or natural minor, `None` until the estimator has heard something.
+    pub fn estimated_key_mode(&self) -> Option<music::ScaleMode> {
+        self.key_estimator.estimate().map(|estimate| estimate.mode)
+    }
+
+    /// Correlation between the heard pitch classes and the key profile, between -1 and 1.
+    pub fn estimated_key_confidence(&self) -> f32 {
+        self.key_estimator
+            .estimate()
+            .map_or(0.0, |estimate| estimate.confidence)
+    }
+
+    /// Use a 12-note tuning with A4 at `a4_frequency` Hz. For temperaments other than equal,
+    /// `tonic` is the note that the pure intervals are relative to.
+    pub fn set_tuning(
+        &mut self,
+        temperament: tuning::Temperament,
+        tonic: music::NoteName,
+        a4_frequency: f32,
+    ) {
+        self.tuning = tuning::Tuning::new(temperament, tonic, a4_frequency);
+    }
+
+    /// Divide the octave into `divisions` equal steps. Since this is not a 12-note tuning,
+    /// quantization ignores the key and snaps to all steps.
+    pub fn set_equal_division_tuning(
+        &mut self,
+        divisions: usize,
+        tonic: music::NoteName,
+        a4_frequency: f32,
+    ) {
+        self.tuning = tuning::Tuning::equal_division(divisions, tonic, a4_frequency);
+    }
+
+    /// Use a tuning from the contents of a Scala `.scl` file and optionally a `.kbm`
+    /// keyboard mapping. `a4_frequency` is only used without a `.kbm` file, otherwise
+    /// the reference frequency of the mapping wins. Unless the result is a plain 12-note
+    /// tuning, quantization ignores the key and snaps to all notes of the scale.
+    pub fn set_scala_tuning(
+        &mut self,
+        scl: String,
+        kbm: Option<String>,
+        a4_frequency: f32,
+    ) -> Result<(), JsError> {
+        self.tuning = tuning::tuning_from_scala(&scl, kbm.as_deref(), a4_frequency)?;
+        Ok(())
+    }
+
     pub fn quantize_frequencies(
         &mut self,
         frequencies: Vec<f32>,
         quantization_type: Option<music::FrequencyQuantizationType>,
     ) -> Vec<f32> {
-        music::quantize_frequencies(&frequencies, quantization_type, self.sample_rate)
+        music::quantize_frequencies(
+            &frequencies,
+            quantization_type,
+            &self.key,
+            &self.tuning,
+            self.sample_rate,
+        )
+    }
+
+    /// Set the scale/chord timeline used by `quantize_frequencies_timeline()`.
+    /// See `timeline::Timeline` for the JSON format.
+    pub fn set_quantization_timeline(&mut self, json: String) -> Result<(), JsError> {
+        self.timeline = Some(timeline::Timeline::from_json(&json)?);
+        Ok(())
+    }
+
+    pub fn clear_quantization_timeline(&mut self) {
+        self.timeline = None;
+    }
+
+    /// Like `quantize_frequencies()`, but each frame uses the key that the timeline
+    /// has at that point of the output. `start_seconds` is the output time of the first
+    /// frame, so that a file can be rendered in chunks. Before the first change of the
+    /// timeline (or without a timeline), the current key is used.
+    pub fn quantize_frequencies_timeline(
+        &mut self,
+        frequencies: Vec<f32>,
+        quantization_type: Option<music::FrequencyQuantizationType>,
+        start_seconds: f32,
+    ) -> Vec<f32> {
+        let seconds_per_frame = self.hop_size as f32 * self.time_stretch / self.sample_rate as f32;
+
+        let mut result = Vec::with_capacity(frequencies.len());
+        for (i, frame) in frequencies.chunks(self.n_waves).enumerate() {
+            let seconds = start_seconds + i as f32 * seconds_per_frame;
+            let key = self
+                .timeline
+                .as_ref()
+                .and_then(|timeline| timeline.key_at(seconds))
+                .unwrap_or(&self.key);
+            result.extend(music::quantize_frequencies(
+                frame,
+                quantization_type,
+                key,
+                &self.tuning,
+                self.sample_rate,
+            ));
+        }
+        result
+    }
+
+    /// Like `quantize_frequencies()`, but no two waves of a frame get the same note,
+    /// so n waves always give an n-note chord. The waves keep their order and the total
+    /// deviation in cents is minimized. With a positive `voice_leading_weight`, moving away
+    /// from the previous frame's notes is penalized (per cent, relative to the deviation).
+    pub fn quantize_frequencies_polyphonic(
+        &mut self,
+        frequencies: Vec<f32>,
+        quantization_type: music::FrequencyQuantizationType,
+        voice_leading_weight: f32,
+    ) -> Vec<f32> {
+        let allowed_frequencies = music::allowed_frequencies(
+            &quantization_type.to_scale(&self.key),
+            &self.tuning,
+            self.sample_rate,
+        );
+
+        let mut result = Vec::with_capacity(frequencies.len());
+        for frame in frequencies.chunks(self.n_waves) {
+            let previous_notes = if self.previous_polyphonic_notes.len() == frame.len() {
+                Some(self.previous_polyphonic_notes.as_slice())
+            } else {
+                None
+            };
+            let quantized = music::quantize_frequencies_distinct(
+                frame,
+                &allowed_frequencies,
+                previous_notes,
+                voice_leading_weight,
+            );
+            result.extend_from_slice(&quantized);
+            self.previous_polyphonic_notes = quantized;
+        }
+        result
+    }
+
+    /// Pass in raw MIDI bytes, e.g. from a Web MIDI `midimessage` event.
+    /// Note-on and note-off messages update the notes used by
+    /// `quantize_frequencies_to_held_notes()`, everything else is ignored.
+    pub fn handle_midi_message(&mut self, bytes: Vec<u8>) {
+        self.held_notes.handle_midi_bytes(&bytes);
+    }
+
+    /// Snaps each wave to the pitch classes of the currently held notes, in any octave.
+    /// Holding a chord harmonizes the speech with it, like a vocoder.
+    /// If no notes are held, the frequencies are returned unchanged.
+    pub fn quantize_frequencies_to_held_notes(&mut self, frequencies: Vec<f32>) -> Vec<f32> {
+        if self.held_notes.is_empty() {
+            return frequencies;
+        }
+        music::quantize_frequencies_to_notes(
+            &frequencies,
+            &self.held_notes.pitch_classes(),
+            &self.tuning,
+            self.sample_rate,
+        )
+    }
+
+    /// Remove all targets of the morph used by `quantize_frequencies_morph()`,
+    /// to be followed by `add_quantization_morph_target()` calls.
+    pub fn clear_quantization_morph(&mut self) {
+        self.quantization_morph.targets.clear();
+    }
+
+    /// Add a target to the morph used by `quantize_frequencies_morph()`. `None` means
+    /// unquantized. `weight_curve` is a flattened list of (morph position, weight) pairs.
+    /// If it's empty, the target peaks at position i for the i-th target and crossfades
+    /// linearly with its neighbors.
+    pub fn add_quantization_morph_target(
+        &mut self,
+        quantization_type: Option<music::FrequencyQuantizationType>,
+        weight_curve: Vec<f32>,
+    ) {
+        let target = match quantization_type {
+            None => morph::QuantizationTarget::Unquantized,
+            Some(quantization_type) => morph::QuantizationTarget::Type(quantization_type),
+        };
+        self.add_morph_target(target, &weight_curve);
+    }
+
+    /// Like `add_quantization_morph_target()`, but snaps to any scale, given as semitones
+    /// above the root.
+    pub fn add_quantization_morph_scale_target(
+        &mut self,
+        root: music::NoteName,
+        intervals: Vec<i32>,
+        weight_curve: Vec<f32>,
+    ) {
+        let notes = music::Key::custom(root, &intervals).scale();
+        self.add_morph_target(morph::QuantizationTarget::Notes(notes), &weight_curve);
+    }
+
+    /// Morph between the quantization targets. The quantized versions are averaged in the
+    /// cents domain, weighted by the targets' weight curves at `position`.
+    pub fn quantize_frequencies_morph(&mut self, frequencies: Vec<f32>, position: f32) -> Vec<f32> {
+        self.quantization_morph.apply(
+            &frequencies,
+            position,
+            &self.key,
+            &self.tuning,
+            self.sample_rate,
+        )
     }
 
+    /// The original quantization slider, a preset of `quantize_frequencies_morph()`:
+    /// 0 is unquantized, 1 chromatic, 2 diatonic and 3 pentatonic.
     pub fn quantize_frequencies_continuous(
         &mut self,
         frequencies: Vec<f32>,
         quantization_strength: f32,
     ) -> Vec<f32> {
-        let quantized_versions = [
-            None,
-            Some(music::FrequencyQuantizationType::Chromatic),
-            Some(music::FrequencyQuantizationType::Diatonic),
-            Some(music::FrequencyQuantizationType::Pentatonic),
-        ]
-        .map(|quantization_type| {
-            music::quantize_frequencies(&frequencies, quantization_type, self.sample_rate)
-        });
-        let max_strength = quantized_versions.len() as f32 - 1.0;
-
-        if quantization_strength < 0.0 || quantization_strength > max_strength {
+        let max_strength = 3.0;
+        if !(0.0..=max_strength).contains(&quantization_strength) {
             panic!(
                 "quantization_strength must be between 0 and {}, got {}",
                 max_strength, quantization_strength
             );
         }
 
-        let interpolation_weights = [
-            // Unquantized version fades out quickly
-            remap(quantization_strength, 0.0, 3.0, 1.0, 0.0),
-            // Chromatic peaks at quantization_strength==1 and then fades
-            if quantization_strength <= 1.0 {
-                remap(quantization_strength, 0.0, 1.0, 0.0, 3.0)
-            } else {
-                remap(quantization_strength, 1.0, 2.0, 3.0, 0.0)
-            },
-            // Diatonic peaks at quantization_strength==2 and then fades
-            if quantization_strength <= 2.0 {
-                remap(quantization_strength, 1.0, 2.0, 0.0, 3.0)
-            } else {
-                remap(quantization_strength, 2.0, 3.0, 3.0, 0.0)
-            },
-            // Pentatonic comes in at quantization_strength==2 and then gets stronger
-            remap(quantization_strength, 2.0, 3.0, 0.0, 3.0),
-        ];
-
-        let total_weight: f32 = interpolation_weights.iter().sum();
-
-        let mut result = vec![0.0; frequencies.len()];
-        for (i, quantized_version) in quantized_versions.iter().enumerate() {
-            for (j, (result, quantized)) in
-                result.iter_mut().zip(quantized_version.iter()).enumerate()
-            {
-                *result += quantized * (interpolation_weights[i] / total_weight);
-            }
-        }
+        morph::QuantizationMorph::strength_preset().apply(
+            &frequencies,
+            quantization_strength,
+            &self.key,
+            &self.tuning,
+            self.sample_rate,
+        )
+    }
 
-        result
+    /// The mapping from input to output frequency of `quantize_frequencies()` with the current
+    /// key and tuning, for plotting. Returns `n_points` input frequencies in Hz, evenly spaced
+    /// in cents between `min_hz` and `max_hz`, followed by the corresponding outputs in Hz.
+    pub fn quantization_curve(
+        &mut self,
+        min_hz: f32,
+        max_hz: f32,
+        n_points: usize,
+        quantization_type: Option<music::FrequencyQuantizationType>,
+    ) -> Vec<f32> {
+        self.quantization_curve_with(min_hz, max_hz, n_points, |converter, frequencies| {
+            converter.quantize_frequencies(frequencies, quantization_type)
+        })
+    }
+
+    /// Like `quantization_curve()`, but for `quantize_frequencies_continuous()`.
+    pub fn quantization_curve_continuous(
+        &mut self,
+        min_hz: f32,
+        max_hz: f32,
+        n_points: usize,
+        quantization_strength: f32,
+    ) -> Vec<f32> {
+        self.quantization_curve_with(min_hz, max_hz, n_points, |converter, frequencies| {
+            converter.quantize_frequencies_continuous(frequencies, quantization_strength)
+        })
+    }
+
+    /// Configure `retune()`. A wave only switches to a new note once the unquantized frequency
+    /// is `hysteresis_cents` past the boundary between the old note and the new one, and then
+    /// glides to the new note over `retune_seconds`. Zero for both gives plain quantization.
+    pub fn set_retune_settings(&mut self, hysteresis_cents: f32, retune_seconds: f32) {
+        self.retuner.hysteresis_cents = hysteresis_cents;
+        self.retuner.retune_seconds = retune_seconds;
+    }
+
+    /// Make quantization stateful, see `set_retune_settings()`. Takes the frequencies before
+    /// and after any of the quantization methods, so it works with all of them.
+    /// `quantization_type` is the type used with the current key, so that a note that is no
+    /// longer in the key isn't held. Pass None when quantizing to something else,
+    /// e.g. held notes or a timeline, and notes are then held regardless.
+    pub fn retune(
+        &mut self,
+        unquantized: Vec<f32>,
+        quantized: Vec<f32>,
+        quantization_type: Option<music::FrequencyQuantizationType>,
+    ) -> Vec<f32> {
+        assert_eq!(unquantized.len(), quantized.len());
+        let seconds_per_frame = self.hop_size as f32 * self.time_stretch / self.sample_rate as f32;
+        let allowed_notes = match quantization_type {
+            Some(quantization_type) => music::allowed_frequencies(
+                &quantization_type.to_scale(&self.key),
+                &self.tuning,
+                self.sample_rate,
+            ),
+            None => Vec::new(),
+        };
+
+        unquantized
+            .chunks(self.n_waves)
+            .zip(quantized.chunks(self.n_waves))
+            .flat_map(|(unquantized, quantized)| {
+                self.retuner.process_frame(
+                    unquantized,
+                    quantized,
+                    &allowed_notes,
+                    seconds_per_frame,
+                )
+            })
+            .collect()
+    }
+
+    /// Tuner-style analysis of the last frame of `frequencies`: the nearest note of the
+    /// current tuning for each wave and how far off it is. Silent waves (frequency or
+    /// magnitude 0, e.g. a track that is fading out) are left out, use `NoteAnalysis.wave`
+    /// to tell the waves apart.
+    pub fn analyze_notes(
+        &self,
+        frequencies: Vec<f32>,
+        magnitudes: Vec<f32>,
+    ) -> Vec<music::NoteAnalysis> {
+        assert_eq!(frequencies.len(), magnitudes.len());
+        let to_hz = self.sample_rate as f32 / (2. * std::f32::consts::PI);
+        let last_frame = frequencies.chunks(self.n_waves).last().unwrap_or(&[]);
+        let last_magnitudes = magnitudes.chunks(self.n_waves).last().unwrap_or(&[]);
+
+        last_frame
+            .iter()
+            .zip(last_magnitudes)
+            .enumerate()
+            .filter(|(_, (_, magnitude))| **magnitude > 0.0)
+            .filter_map(|(wave, (frequency, _))| {
+                music::analyze_note(wave, frequency * to_hz, &self.tuning)
+            })
+            .collect()
     }
 
     /// Add depth by lowering the first frequencies more than the last ones.
@@ -126,6 +803,48 @@ impl SineWaveSpeechConverter {
         music::add_depth(&frequencies, width)
     }
 
+    /// Shift formants, meant to be applied between analysis and synthesis.
+    /// `scale` multiplies all frequencies (vocal tract length change, e.g. ~1.4 for adult->child),
+    /// `warping_curve` is a flattened list of (input Hz, output Hz) pairs
+    /// and `offsets` contains one offset per wave.
+    pub fn transform_formants(
+        &mut self,
+        frequencies: Vec<f32>,
+        scale: f32,
+        warping_curve: Vec<f32>,
+        offsets: Vec<f32>,
+        offset_unit: formant_shift::FormantOffsetUnit,
+    ) -> Vec<f32> {
+        let transformation = formant_shift::FormantTransformation {
+            scale,
+            warping_curve: formant_shift::warping_curve_from_flat(&warping_curve),
+            offsets,
+            offset_unit,
+        };
+        transformation.apply(&frequencies, self.n_waves, self.sample_rate)
+    }
+
+    /// Octave-folds each wave into its own range, e.g. the first wave into C3-C4 and the second
+    /// into C4-C5, which keeps the pitch classes but avoids shrill or muddy registers.
+    /// `ranges_hz` is a flattened list of (low, high) pairs with 0 < low < high, one per wave.
+    /// Waves without a range are left alone.
+    pub fn fold_registers(&mut self, frequencies: Vec<f32>, ranges_hz: Vec<f32>) -> Vec<f32> {
+        let to_normalized = (2. * std::f32::consts::PI) / self.sample_rate as f32;
+        let ranges: Vec<(f32, f32)> = music::ranges_from_flat(&ranges_hz)
+            .into_iter()
+            .map(|(low, high)| (low * to_normalized, high * to_normalized))
+            .collect();
+
+        frequencies
+            .chunks(self.n_waves)
+            .flat_map(|frame| music::fold_registers(frame, &ranges))
+            .collect()
+    }
+
+    /// Returns the synthesized audio followed by the last phase of each wave.
+    /// When `time_stretch` is not 1, the number of audio samples can vary between calls
+    /// because the fractional part of the synthesis hop is carried over to the next call,
+    /// so take the audio as everything except the last `n_waves` values.
     pub fn synthesize(
         &mut self,
         frequencies: Vec<f32>,
@@ -147,24 +866,178 @@ impl SineWaveSpeechConverter {
             *val /= compensation;
         }
 
-        let (sws, last_phases) = synthesize(
+        let (sws, last_phases, next_offset) = synthesize(
             frequencies.view(),
             magnitudes.view(),
-            self.hop_size,
+            self.hop_size as f32 * self.time_stretch,
+            self.synthesis_offset,
             f32::sin,
             Some(Array::from_vec(first_phases)),
         );
+        self.synthesis_offset = next_offset;
 
         let mut result = sws.to_vec();
         result.append(&mut last_phases.to_vec());
         result
     }
+
+    /// Export the waves as a Standard MIDI File with one track (and channel) per wave,
+    /// so with at most 15 waves. Each wave is split into notes of the nearest MIDI pitch
+    /// in 12-TET with A4 = 440 Hz, ignoring the tuning, with the velocity given by the
+    /// magnitude. Frames quieter than `silence_threshold_db` relative to the loudest
+    /// frame are rests. With `pitch_bend`, the exact frequencies are kept as pitch bends
+    /// with a range of 2 semitones.
+    pub fn export_midi(
+        &mut self,
+        frequencies: Vec<f32>,
+        magnitudes: Vec<f32>,
+        pitch_bend: bool,
+        silence_threshold_db: f32,
+    ) -> Result<Vec<u8>, JsError> {
+        assert_eq!(frequencies.len(), magnitudes.len());
+        let n_steps: usize = frequencies.len() / self.n_waves;
+        let to_hz = self.sample_rate as f32 / (2. * std::f32::consts::PI);
+
+        let frequencies_hz = Array2::from_shape_vec((n_steps, self.n_waves), frequencies)
+            .unwrap()
+            .mapv(|f| f * to_hz);
+        let magnitudes = Array2::from_shape_vec((n_steps, self.n_waves), magnitudes).unwrap();
+
+        let options = midi::MidiExportOptions {
+            silence_threshold_db,
+            pitch_bend,
+            ..Default::default()
+        };
+        // Use the synthesis hop so that the notes line up with the synthesized audio.
+        let seconds_per_frame = self.hop_size as f32 * self.time_stretch / self.sample_rate as f32;
+        Ok(midi::export_midi(
+            frequencies_hz.view(),
+            magnitudes.view(),
+            seconds_per_frame,
+            &options,
+        )?)
+    }
 }
 
-fn remap(value: f32, from_min: f32, from_max: f32, to_min: f32, to_max: f32) -> f32 {
-    // For our purposes, it's useful to silently return 0.0 if the value is outside the range
-    if value < from_min || value > from_max {
-        return 0.0;
+impl SineWaveSpeechConverter {
+    fn lpc_frequencies_and_magnitudes(&self, audio: &Array1<f32>) -> (Array2<f32>, Array2<f32>) {
+        let (frequencies, mut magnitudes) = if self.pitch_synchronous {
+            self.pitch_synchronous_frequencies_and_magnitudes(audio)
+        } else {
+            let (lpc_coefficients, gain, _residual) = self.fit_lpc(audio);
+            lpc::lpc_coefficients_to_frequencies(
+                lpc_coefficients.view(),
+                gain.view(),
+                self.n_waves,
+                &self.pole_selection,
+                self.magnitude_estimator,
+                self.analysis_config.warping,
+            )
+        };
+
+        match self.magnitude_estimator {
+            lpc::MagnitudeEstimator::PoleGain => {
+                // Normalize magnitudes by the number of waves because otherwise the total
+                // magnitude increases with the number of waves. Not sure if this is the proper
+                // way to do it but it works.
+                // Note we also apply compression to the final waveform in synthesize().
+                magnitudes.mapv_inplace(|x| x / self.n_waves as f32);
+            }
+            lpc::MagnitudeEstimator::BandEnergy => {
+                let frames = lpc::analysis_frames(audio, self.hop_size, &self.analysis_config);
+                magnitudes = lpc::band_energy_magnitudes(
+                    frames.view(),
+                    self.analysis_config.window(self.hop_size).view(),
+                    frequencies.view(),
+                );
+            }
+            lpc::MagnitudeEstimator::Envelope | lpc::MagnitudeEstimator::BandwidthNormalized => {}
+        }
+
+        (frequencies, magnitudes)
+    }
+
+    fn pitch_synchronous_frequencies_and_magnitudes(
+        &self,
+        audio: &Array1<f32>,
+    ) -> (Array2<f32>, Array2<f32>) {
+        let frames = lpc::analysis_frames(audio, self.hop_size, &self.analysis_config);
+        let periods = self.cepstral_analyzer.periods(frames.view());
+        let epochs = pitch_synchronous::find_epochs(audio.view(), &periods, self.hop_size);
+
+        let (lpc_coefficients, gain) = pitch_synchronous::fit_lpc_at_epochs(
+            audio,
+            &epochs,
+            self.lpc_order(),
+            self.hop_size,
+            &self.analysis_config,
+        );
+        let (frequencies, magnitudes) = lpc::lpc_coefficients_to_frequencies(
+            lpc_coefficients.view(),
+            gain.view(),
+            self.n_waves,
+            &self.pole_selection,
+            self.magnitude_estimator,
+            self.analysis_config.warping,
+        );
+
+        pitch_synchronous::resample_tracks(
+            frequencies.view(),
+            magnitudes.view(),
+            &epochs,
+            frames.nrows(),
+            self.hop_size,
+        )
+    }
+
+    fn lpc_order(&self) -> usize {
+        self.lpc_order.unwrap_or(self.n_waves * 2)
+    }
+
+    fn fit_lpc(&self, audio: &Array1<f32>) -> (Array2<f32>, Array1<f32>, Array1<f32>) {
+        lpc::fit_lpc(
+            audio,
+            self.lpc_order(),
+            self.hop_size,
+            &self.analysis_config,
+        )
+    }
+
+    fn quantization_curve_with(
+        &mut self,
+        min_hz: f32,
+        max_hz: f32,
+        n_points: usize,
+        quantize: impl FnOnce(&mut Self, Vec<f32>) -> Vec<f32>,
+    ) -> Vec<f32> {
+        let to_hz = self.sample_rate as f32 / (2. * std::f32::consts::PI);
+        let inputs_hz = music::log_spaced_frequencies(min_hz, max_hz, n_points);
+
+        let outputs = quantize(self, inputs_hz.iter().map(|f| f / to_hz).collect());
+
+        let mut result = inputs_hz;
+        result.extend(outputs.iter().map(|f| f * to_hz));
+        result
+    }
+
+    fn apply_key_estimate(&mut self) {
+        if self.key_locked {
+            return;
+        }
+        if let Some(estimate) = self.key_estimator.estimate() {
+            self.key = estimate.key();
+        }
+    }
+
+    fn add_morph_target(&mut self, target: morph::QuantizationTarget, weight_curve: &[f32]) {
+        let weight_curve = if weight_curve.is_empty() {
+            morph::triangular_weight_curve(self.quantization_morph.targets.len())
+        } else {
+            morph::weight_curve_from_flat(weight_curve)
+        };
+        self.quantization_morph.targets.push(morph::MorphTarget {
+            target,
+            weight_curve,
+        });
     }
-    (value - from_min) / (from_max - from_min) * (to_max - to_min) + to_min
 }
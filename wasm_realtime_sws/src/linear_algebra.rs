@@ -167,9 +167,9 @@ pub mod tests {
             (
                 array![2., -2., -1.,],
                 array![
-                    Complex::new(-(2. as f32).sqrt(), 0.),
+                    Complex::new(-2_f32.sqrt(), 0.),
                     Complex::new(1., 0.),
-                    Complex::new((2. as f32).sqrt(), 0.),
+                    Complex::new(2_f32.sqrt(), 0.),
                 ],
             ),
             // x^3 - x^2 - 2x + 3
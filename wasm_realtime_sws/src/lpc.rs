@@ -1,79 +1,93 @@
 use nalgebra::{Complex, ComplexField};
 use ndarray::{concatenate, prelude::*};
+use wasm_bindgen::prelude::*;
 
 use crate::{
     linear_algebra::{find_roots, solve_toeplitz, ToeplitzError},
-    signal_processing::{autocorrelate, hann_window, lfilter},
+    signal_processing::{
+        autocorrelate, dtft_magnitude, lfilter, magnitude_spectrum, warp_frequency,
+        warped_autocorrelate, warped_lfilter, WindowType,
+    },
 };
 
+/// How the audio is cut into frames and preprocessed before fitting the LPC filters.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AnalysisConfig {
+    pub window_type: WindowType,
+    /// Defaults to twice the hop size. Must be at least the hop size.
+    pub window_size: Option<usize>,
+    /// Coefficient `c` of the pre-emphasis filter 1 - c z^-1, which boosts high frequencies
+    /// so that the higher formants are found too. 0 turns pre-emphasis off.
+    pub pre_emphasis: f32,
+    /// Allpass warping factor of the frequency axis. Positive values give low frequencies
+    /// more resolution, see `bark_warping()`. 0 is plain LPC.
+    pub warping: f32,
+}
+
+impl Default for AnalysisConfig {
+    fn default() -> Self {
+        // Same as the original Python implementation
+        AnalysisConfig {
+            window_type: WindowType::Hann,
+            window_size: None,
+            pre_emphasis: 0.9,
+            warping: 0.0,
+        }
+    }
+}
+
+/// The warping factor that approximates the Bark scale at the given sample rate,
+/// e.g. 0.40 at 8 kHz and 0.76 at 44.1 kHz. From J. O. Smith and J. S. Abel,
+/// "Bark and ERB bilinear transforms", 1999.
+pub fn bark_warping(sample_rate: usize) -> f32 {
+    let sample_rate_khz = sample_rate as f32 / 1000.0;
+    1.0674 * (2.0 / std::f32::consts::PI * (0.06583 * sample_rate_khz).atan()).sqrt() - 0.1916
+}
+
+impl AnalysisConfig {
+    pub fn window_size(&self, hop_size: usize) -> usize {
+        let window_size = self.window_size.unwrap_or(2 * hop_size);
+        assert!(
+            window_size >= hop_size,
+            "window size ({}) must be at least the hop size ({})",
+            window_size,
+            hop_size
+        );
+        window_size
+    }
+
+    pub fn window(&self, hop_size: usize) -> Array1<f32> {
+        self.window_type.window(self.window_size(hop_size))
+    }
+}
+
 // Original Python: def fit_lpc(audio: np.ndarray, p=12, hop_size=DEFAULT_HOP_SIZE, window_size=None):
 pub fn fit_lpc(
     audio: &Array1<f32>,
     p: usize,
     hop_size: usize,
-    window_size: Option<usize>,
+    config: &AnalysisConfig,
 ) -> (Array2<f32>, Array1<f32>, Array1<f32>) {
-    let window_size = window_size.unwrap_or(2 * hop_size);
+    let window_size = config.window_size(hop_size);
+    let window = config.window(hop_size);
     let n_hops = audio.len() / hop_size;
 
-    // Original Python:
-    // audio = np.concatenate([
-    //     np.zeros((window_size - hop_size) // 2),
-    //     audio,
-    //     np.zeros((window_size - hop_size) // 2),
-    // ])
-    let pad_size = (window_size - hop_size) / 2;
-    let audio = concatenate![
-        Axis(0),
-        Array1::zeros(pad_size),
-        audio.to_owned(),
-        Array1::zeros(pad_size)
-    ];
+    let audio = prepare_audio(audio, hop_size, config);
 
     let mut lpc_coefficients = Array2::zeros((n_hops, p + 1));
     let mut gain = Array1::zeros(n_hops);
     let mut residual = Array1::zeros((n_hops - 1) * hop_size + window_size);
 
-    // Original Python: audio = scipy.signal.lfilter(np.array([1.0, -0.9]), 1, audio)
-    let audio = lfilter(&array![1.0, -0.9], &audio);
-
     for hop in 0..n_hops {
         let cur_audio = audio.slice(s![hop * hop_size..hop * hop_size + window_size]);
-        let windowed_audio = &cur_audio * &hann_window(window_size);
+        let windowed_audio = &cur_audio * &window;
 
-        // Original Python: autocorrelated = scipy.signal.correlate(windowed_audio, windowed_audio)
-        let autocorrelated = autocorrelate(windowed_audio.view());
-        let autocorrelated = autocorrelated.slice(s![..p + 1]);
-
-        // Original Python:
-        // try:
-        //     cur_lpc_coefficients = scipy.linalg.solve_toeplitz(
-        //         autocorrelated[:p], autocorrelated[1 : p + 1]
-        //     )
-        // except scipy.linalg.LinAlgError:  # "Singular principal minor"
-        //     print("Singular principal minor")
-        //     continue
-
-        // construct the toeplitz matrix argument represented as 1d array of the "edge"
-        let toeplitz = concatenate![
-            Axis(0),
-            autocorrelated.slice(s![1..p;-1]),
-            autocorrelated.slice(s![..p]),
-        ];
-
-        let cur_lpc_coefficients =
-            match solve_toeplitz(toeplitz.view(), autocorrelated.slice(s![1..p + 1])) {
-                Ok(coeffs) => coeffs,
-                Err(ToeplitzError::SingularPrincipalMinor) => {
-                    println!("Singular principal minor");
-                    continue;
-                }
+        let (cur_lpc_coefficients, cur_gain, cur_residual) =
+            match fit_frame(&windowed_audio, p, config.warping) {
+                Some(fit) => fit,
+                None => continue,
             };
 
-        let cur_lpc_coefficients = concatenate![Axis(0), arr1(&[1.0]), -cur_lpc_coefficients];
-        let cur_residual = lfilter(&cur_lpc_coefficients, &windowed_audio);
-        let cur_gain = (cur_residual.mapv(|x| x.powi(2)).mean().unwrap()).sqrt();
-
         lpc_coefficients
             .slice_mut(s![hop, ..])
             .assign(&cur_lpc_coefficients);
@@ -92,15 +106,231 @@ pub fn fit_lpc(
     (lpc_coefficients, gain, residual)
 }
 
+/// Fit LPC of order `p` to a single windowed frame. Returns the coefficients, the gain
+/// and the residual, or None if the autocorrelation matrix is singular.
+pub fn fit_frame(
+    windowed_audio: &Array1<f32>,
+    p: usize,
+    warping: f32,
+) -> Option<(Array1<f32>, f32, Array1<f32>)> {
+    let autocorrelated = if warping == 0.0 {
+        // Original Python: autocorrelated = scipy.signal.correlate(windowed_audio, windowed_audio)
+        autocorrelate(windowed_audio.view())
+            .slice(s![..p + 1])
+            .to_owned()
+    } else {
+        warped_autocorrelate(windowed_audio.view(), p, warping)
+    };
+
+    // Original Python:
+    // try:
+    //     cur_lpc_coefficients = scipy.linalg.solve_toeplitz(
+    //         autocorrelated[:p], autocorrelated[1 : p + 1]
+    //     )
+    // except scipy.linalg.LinAlgError:  # "Singular principal minor"
+    //     print("Singular principal minor")
+    //     continue
+
+    // construct the toeplitz matrix argument represented as 1d array of the "edge"
+    let toeplitz = concatenate![
+        Axis(0),
+        autocorrelated.slice(s![1..p;-1]),
+        autocorrelated.slice(s![..p]),
+    ];
+
+    let cur_lpc_coefficients =
+        match solve_toeplitz(toeplitz.view(), autocorrelated.slice(s![1..p + 1])) {
+            Ok(coeffs) => coeffs,
+            Err(ToeplitzError::SingularPrincipalMinor) => {
+                println!("Singular principal minor");
+                return None;
+            }
+        };
+
+    let cur_lpc_coefficients = concatenate![Axis(0), arr1(&[1.0]), -cur_lpc_coefficients];
+    let cur_residual = if warping == 0.0 {
+        lfilter(&cur_lpc_coefficients, windowed_audio)
+    } else {
+        warped_lfilter(&cur_lpc_coefficients, windowed_audio, warping)
+    };
+    let cur_gain = (cur_residual.mapv(|x| x.powi(2)).mean().unwrap()).sqrt();
+
+    Some((cur_lpc_coefficients, cur_gain, cur_residual))
+}
+
+/// Pad the audio so that the windows are centered on the hops and apply pre-emphasis.
+fn prepare_audio(audio: &Array1<f32>, hop_size: usize, config: &AnalysisConfig) -> Array1<f32> {
+    let window_size = config.window_size(hop_size);
+
+    // Original Python:
+    // audio = np.concatenate([
+    //     np.zeros((window_size - hop_size) // 2),
+    //     audio,
+    //     np.zeros((window_size - hop_size) // 2),
+    // ])
+    // If the difference is odd, the extra sample goes at the end
+    let left_pad_size = (window_size - hop_size) / 2;
+    let right_pad_size = window_size - hop_size - left_pad_size;
+    let audio = concatenate![
+        Axis(0),
+        Array1::zeros(left_pad_size),
+        audio.to_owned(),
+        Array1::zeros(right_pad_size)
+    ];
+
+    // Original Python: audio = scipy.signal.lfilter(np.array([1.0, -0.9]), 1, audio)
+    lfilter(&array![1.0, -config.pre_emphasis], &audio)
+}
+
+/// The windowed audio that `fit_lpc()` analyzes for each hop, shape (n_hops, window_size).
+pub fn analysis_frames(
+    audio: &Array1<f32>,
+    hop_size: usize,
+    config: &AnalysisConfig,
+) -> Array2<f32> {
+    let window = config.window(hop_size);
+    let n_hops = audio.len() / hop_size;
+
+    let audio = prepare_audio(audio, hop_size, config);
+    Array2::from_shape_fn((n_hops, window.len()), |(hop, i)| {
+        audio[hop * hop_size + i] * window[i]
+    })
+}
+
+/// The windowed audio that `fit_lpc()` analyzes for the last hop.
+pub fn last_analysis_frame(
+    audio: &Array1<f32>,
+    hop_size: usize,
+    config: &AnalysisConfig,
+) -> Array1<f32> {
+    let frames = analysis_frames(audio, hop_size, config);
+    assert!(frames.nrows() > 0, "need at least one hop of audio");
+    frames.row(frames.nrows() - 1).to_owned()
+}
+
+/// The all-pole magnitude response `gain / |A(e^{jw})|` at the given normalized frequencies,
+/// i.e. the spectral envelope that the LPC fit models. `warping` must match the analysis.
+/// Frames where the fit failed (left as zeros by `fit_lpc()`) give a flat envelope of zeros.
+pub fn lpc_envelope(
+    lpc_coefficients: ArrayView1<f32>,
+    gain: f32,
+    frequencies: &[f32],
+    warping: f32,
+) -> Vec<f32> {
+    if lpc_coefficients[0] == 0.0 {
+        return vec![0.0; frequencies.len()];
+    }
+    frequencies
+        .iter()
+        .map(|w| gain / dtft_magnitude(lpc_coefficients, warp_frequency(*w, warping)))
+        .collect()
+}
+
+/// Which poles become waves when the LPC fit finds more poles than there are waves.
+#[wasm_bindgen]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PoleSelection {
+    /// The lowest frequencies, which is what you get with an LPC order of `2 * n_waves`.
+    Lowest,
+    /// The largest magnitudes.
+    Strongest,
+    /// The smallest bandwidths, i.e. the sharpest peaks of the envelope.
+    Narrowest,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoleSelectionConfig {
+    pub selection: PoleSelection,
+    /// Poles outside of this range (normalized frequency) are never used.
+    pub min_frequency: f32,
+    pub max_frequency: f32,
+    /// Poles with a larger bandwidth (normalized frequency) are never used.
+    pub max_bandwidth: f32,
+    /// Optional (low, high) normalized frequency band per wave, like F1 in 200-1000 Hz.
+    /// Each of these waves takes the best pole inside its band that isn't taken by an
+    /// earlier wave, and stays empty if there is none. Waves without a band take the
+    /// best of the remaining poles.
+    pub bands: Vec<(f32, f32)>,
+}
+
+impl Default for PoleSelectionConfig {
+    fn default() -> Self {
+        PoleSelectionConfig {
+            selection: PoleSelection::Lowest,
+            min_frequency: 0.0,
+            max_frequency: std::f32::consts::PI,
+            max_bandwidth: f32::INFINITY,
+            bands: Vec::new(),
+        }
+    }
+}
+
+/// How loud the wave of each formant is.
+///
+/// All estimators except `PoleGain` are in the same units as the (pre-emphasized) input
+/// audio and don't depend on the number of waves, the hop size or the window size.
+#[wasm_bindgen]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MagnitudeEstimator {
+    /// `gain / (1 - |pole|)`, the original estimator. Explodes for poles close to the unit
+    /// circle, which is why the converter divides it by the number of waves and synthesis
+    /// compresses the output.
+    PoleGain,
+    /// The LPC envelope `gain / |A(e^{jw})|` at the pole frequency: the spectral level
+    /// of the formant peak, relative to white noise with the same RMS as the residual.
+    Envelope,
+    /// `gain * sqrt(2 / (1 - |pole|))`, proportional to the square root of the energy under
+    /// the resonance peak, so narrow peaks don't dominate.
+    BandwidthNormalized,
+    /// The amplitude of a sine wave with the same energy as the input spectrum between the
+    /// neighboring formants, see `band_energy_magnitudes()`. Poles are selected using
+    /// the envelope since this needs the audio.
+    BandEnergy,
+}
+
+/// `pole` is on the linear frequency axis, `warped_pole` is the root of the LPC polynomial.
+fn pole_magnitude(
+    estimator: MagnitudeEstimator,
+    pole: Complex<f32>,
+    warped_pole: Complex<f32>,
+    gain: f32,
+    lpc_coefficients: ArrayView1<f32>,
+) -> f32 {
+    let radius = pole.abs();
+    match estimator {
+        MagnitudeEstimator::PoleGain => gain / (1. - radius),
+        MagnitudeEstimator::Envelope | MagnitudeEstimator::BandEnergy => {
+            gain / dtft_magnitude(lpc_coefficients, warped_pole.arg())
+        }
+        MagnitudeEstimator::BandwidthNormalized => gain * (2.0 / (1. - radius)).sqrt(),
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Pole {
+    frequency: f32,
+    magnitude: f32,
+    /// The -3 dB bandwidth of the resonance, in normalized frequency.
+    bandwidth: f32,
+}
+
+/// Returns (frequencies, magnitudes), each of shape (n_hops, n_waves). If the LPC order
+/// gives more than `n_waves` poles, they are chosen according to `pole_selection`.
+/// Waves without a pole get a frequency and magnitude of 0. `warping` must match the
+/// analysis, the poles are unwarped back to linear frequencies.
 pub fn lpc_coefficients_to_frequencies(
     lpc_coefficients: ArrayView2<f32>,
     gain: ArrayView1<f32>,
+    n_waves: usize,
+    pole_selection: &PoleSelectionConfig,
+    magnitude_estimator: MagnitudeEstimator,
+    warping: f32,
 ) -> (Array2<f32>, Array2<f32>) {
     let n_hops = lpc_coefficients.len_of(Axis(0));
     let p = lpc_coefficients.len_of(Axis(1)) - 1;
 
-    let mut frequencies: Array2<f32> = Array::zeros((n_hops, p / 2));
-    let mut magnitudes: Array2<f32> = Array::zeros((n_hops, p / 2));
+    let mut frequencies: Array2<f32> = Array::zeros((n_hops, n_waves));
+    let mut magnitudes: Array2<f32> = Array::zeros((n_hops, n_waves));
 
     for hop in 0..n_hops {
         // Note that we reverse the slice because our find_roots() function expects
@@ -122,37 +352,145 @@ pub fn lpc_coefficients_to_frequencies(
             }
         }
 
-        let mut frequencies_and_magnitudes: Vec<(f32, f32)> = roots
+        let poles: Vec<Pole> = roots
             .to_vec()
             .into_iter()
-            .map(|x| (get_frequency(x), gain[hop] / (1. - x.abs())))
+            .map(|warped| {
+                // Undo the allpass warping: z = (z_warped + warping) / (1 + warping * z_warped)
+                let x = (warped + warping) / (warped * warping + 1.0);
+                Pole {
+                    frequency: get_frequency(x),
+                    magnitude: pole_magnitude(
+                        magnitude_estimator,
+                        x,
+                        warped,
+                        gain[hop],
+                        lpc_coefficients.row(hop),
+                    ),
+                    bandwidth: -2.0 * x.abs().ln(),
+                }
+            })
             // Each frequency is repeated twice, once with a positive angle and once
             // with a negative angle. We only want to keep the positive angle.
             // Also filter out frequencies of 0 and pi (see get_frequency()).
-            .filter(|(f, _m)| *f >= 1e-3)
+            .filter(|pole| pole.frequency >= 1e-3)
             .collect();
 
-        if frequencies_and_magnitudes.len() > p / 2 {
+        if poles.len() > p / 2 {
             panic!(
                 "expected at most {:?} positive frequencies, got {:?}: {:?}",
                 p / 2,
-                frequencies_and_magnitudes.len(),
-                frequencies_and_magnitudes
+                poles.len(),
+                poles
             );
         }
 
-        // Sort the frequencies so that the sine waves don't cross when we upsample.
-        frequencies_and_magnitudes.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
-
-        for (i, (f, m)) in frequencies_and_magnitudes.iter().enumerate() {
-            frequencies[[hop, i]] = *f;
-            magnitudes[[hop, i]] = *m;
+        for (i, pole) in select_poles(poles, n_waves, pole_selection)
+            .into_iter()
+            .enumerate()
+        {
+            if let Some(pole) = pole {
+                frequencies[[hop, i]] = pole.frequency;
+                magnitudes[[hop, i]] = pole.magnitude;
+            }
         }
     }
 
     (frequencies, magnitudes)
 }
 
+/// Assign poles to `n_waves` slots according to the config, see `PoleSelectionConfig`.
+fn select_poles(
+    mut poles: Vec<Pole>,
+    n_waves: usize,
+    config: &PoleSelectionConfig,
+) -> Vec<Option<Pole>> {
+    poles.retain(|pole| {
+        (config.min_frequency..=config.max_frequency).contains(&pole.frequency)
+            && pole.bandwidth <= config.max_bandwidth
+    });
+    let key = |pole: &Pole| match config.selection {
+        PoleSelection::Lowest => pole.frequency,
+        PoleSelection::Strongest => -pole.magnitude,
+        PoleSelection::Narrowest => pole.bandwidth,
+    };
+    // Best first
+    poles.sort_by(|a, b| key(a).partial_cmp(&key(b)).unwrap());
+
+    let mut result = vec![None; n_waves];
+    let n_banded = config.bands.len().min(n_waves);
+    for (slot, (low, high)) in config.bands.iter().take(n_banded).enumerate() {
+        if let Some(i) = poles
+            .iter()
+            .position(|pole| (*low..=*high).contains(&pole.frequency))
+        {
+            result[slot] = Some(poles.remove(i));
+        }
+    }
+
+    poles.truncate(n_waves - n_banded);
+    // Sort the frequencies so that the sine waves don't cross when we upsample.
+    poles.sort_by(|a, b| a.frequency.partial_cmp(&b.frequency).unwrap());
+    for (slot, pole) in (n_banded..n_waves).zip(poles) {
+        result[slot] = Some(pole);
+    }
+    result
+}
+
+/// Magnitudes for `MagnitudeEstimator::BandEnergy`. Each wave gets the spectrum of its
+/// analysis frame from halfway to the previous wave to halfway to the next one, converted
+/// to the amplitude of a sine wave with the same energy. Waves with frequency 0 get 0.
+/// `frames` are windowed with `window`, as returned by `analysis_frames()`.
+pub fn band_energy_magnitudes(
+    frames: ArrayView2<f32>,
+    window: ArrayView1<f32>,
+    frequencies: ArrayView2<f32>,
+) -> Array2<f32> {
+    assert_eq!(frames.nrows(), frequencies.nrows());
+    assert_eq!(frames.ncols(), window.len());
+    let fft_size = window.len().next_power_of_two();
+    let bin_width = 2.0 * std::f32::consts::PI / fft_size as f32;
+    // A windowed sine of amplitude A has energy A^2 / 2 * sum(window^2). By Parseval,
+    // the FFT has fft_size times that, split between positive and negative frequencies.
+    let sine_energy_per_amplitude = fft_size as f32 * window.mapv(|x| x * x).sum() / 4.0;
+
+    let mut magnitudes = Array2::zeros(frequencies.raw_dim());
+    for ((frame, frequencies), mut magnitudes) in frames
+        .outer_iter()
+        .zip(frequencies.outer_iter())
+        .zip(magnitudes.outer_iter_mut())
+    {
+        let power = magnitude_spectrum(frame, fft_size).mapv(|x| x * x);
+
+        let mut present: Vec<(usize, f32)> = frequencies
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|(_, f)| *f > 0.0)
+            .collect();
+        present.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+
+        for (i, (wave, frequency)) in present.iter().enumerate() {
+            let low = match i {
+                0 => 0.0,
+                _ => (present[i - 1].1 + frequency) / 2.0,
+            };
+            let high = match present.get(i + 1) {
+                Some((_, next)) => (frequency + next) / 2.0,
+                None => std::f32::consts::PI,
+            };
+            let band_energy: f32 = power
+                .iter()
+                .enumerate()
+                .filter(|(bin, _)| (low..high).contains(&(*bin as f32 * bin_width)))
+                .map(|(_, power)| power)
+                .sum();
+            magnitudes[*wave] = (band_energy / sine_energy_per_amplitude).sqrt();
+        }
+    }
+    magnitudes
+}
+
 #[cfg(test)]
 mod tests {
     use crate::signal_processing::tests::{assert_array1_eq, assert_array2_eq};
@@ -174,6 +512,209 @@ mod tests {
         magnitudes: Vec<f32>,  // flattened from 2D
     }
 
+    #[test]
+    fn test_lpc_envelope() {
+        // A single resonance at 0.5 radians/sample
+        let (r, theta) = (0.95_f32, 0.5_f32);
+        let lpc_coefficients = array![1.0, -2.0 * r * theta.cos(), r * r];
+
+        let envelope = lpc_envelope(lpc_coefficients.view(), 2.0, &[0.0, 0.1, 0.5, 1.5], 0.0);
+        let expected_at_zero = 2.0 / (1.0 - 2.0 * r * theta.cos() + r * r);
+        assert!((envelope[0] - expected_at_zero).abs() < 1e-4);
+        assert!(envelope[2] > 3.0 * envelope[1]);
+        assert!(envelope[2] > 3.0 * envelope[3]);
+    }
+
+    #[test]
+    fn test_odd_window_padding() {
+        // window_size - hop_size is odd, the last hop must still fit
+        let audio = Array1::from_iter((0..512).map(|i| (i as f32 * 0.3).sin()));
+        let config = AnalysisConfig {
+            window_size: Some(255),
+            ..AnalysisConfig::default()
+        };
+        let (lpc_coefficients, gain, _residual) = fit_lpc(&audio, 8, 128, &config);
+        assert_eq!(lpc_coefficients.nrows(), 4);
+        assert!(gain.iter().all(|g| g.is_finite()));
+        assert_eq!(analysis_frames(&audio, 128, &config).shape(), &[4, 255]);
+    }
+
+    #[test]
+    fn test_lpc_envelope_of_silence() {
+        // The fit fails on silence, which must not give NaNs or infinities
+        let (lpc_coefficients, gain, _residual) =
+            fit_lpc(&Array1::zeros(512), 8, 128, &AnalysisConfig::default());
+        let envelope = lpc_envelope(lpc_coefficients.row(3), gain[3], &[0.0, 0.5, 1.5], 0.0);
+        assert_eq!(envelope, vec![0.0, 0.0, 0.0]);
+    }
+
+    /// LPC coefficients of an all-pole filter with the given (radius, angle) pole pairs.
+    fn lpc_from_poles(poles: &[(f32, f32)]) -> Array1<f32> {
+        let mut coefficients = vec![1.0];
+        for (r, theta) in poles {
+            let factor = [1.0, -2.0 * r * theta.cos(), r * r];
+            let mut product = vec![0.0; coefficients.len() + 2];
+            for (i, a) in coefficients.iter().enumerate() {
+                for (j, b) in factor.iter().enumerate() {
+                    product[i + j] += a * b;
+                }
+            }
+            coefficients = product;
+        }
+        Array1::from_vec(coefficients)
+    }
+
+    #[test]
+    fn test_pole_selection() {
+        let lpc = lpc_from_poles(&[(0.8, 0.3), (0.98, 0.6), (0.95, 1.2)]);
+        let lpc = lpc.insert_axis(Axis(0));
+        let gain = array![1.0];
+
+        let select = |config: PoleSelectionConfig| {
+            let (frequencies, _) = lpc_coefficients_to_frequencies(
+                lpc.view(),
+                gain.view(),
+                2,
+                &config,
+                MagnitudeEstimator::PoleGain,
+                0.0,
+            );
+            frequencies.row(0).to_vec()
+        };
+        let default = PoleSelectionConfig::default();
+
+        let lowest = select(default.clone());
+        assert!((lowest[0] - 0.3).abs() < 1e-3 && (lowest[1] - 0.6).abs() < 1e-3);
+        let strongest = select(PoleSelectionConfig {
+            selection: PoleSelection::Strongest,
+            ..default.clone()
+        });
+        assert!((strongest[0] - 0.6).abs() < 1e-3 && (strongest[1] - 1.2).abs() < 1e-3);
+
+        // Only the narrowest pole is left, the other wave is empty
+        let narrow_only = select(PoleSelectionConfig {
+            max_bandwidth: 0.05,
+            ..default.clone()
+        });
+        assert!((narrow_only[0] - 0.6).abs() < 1e-3 && narrow_only[1] == 0.0);
+        let high_only = select(PoleSelectionConfig {
+            min_frequency: 1.0,
+            ..default.clone()
+        });
+        assert!((high_only[0] - 1.2).abs() < 1e-3);
+        let narrowest = select(PoleSelectionConfig {
+            selection: PoleSelection::Narrowest,
+            ..default.clone()
+        });
+        assert!((narrowest[0] - 0.6).abs() < 1e-3 && (narrowest[1] - 1.2).abs() < 1e-3);
+        // No poles in the allowed range at all
+        let none = select(PoleSelectionConfig {
+            min_frequency: 1.5,
+            max_frequency: 2.0,
+            ..default.clone()
+        });
+        assert_eq!(none, vec![0.0, 0.0]);
+
+        // The first wave has no pole in its band, the second takes the best one in its band
+        // and the third takes the best remaining pole
+        let banded = lpc_coefficients_to_frequencies(
+            lpc.view(),
+            gain.view(),
+            3,
+            &PoleSelectionConfig {
+                selection: PoleSelection::Strongest,
+                bands: vec![(1.5, 2.0), (0.2, 1.0)],
+                ..default
+            },
+            MagnitudeEstimator::PoleGain,
+            0.0,
+        )
+        .0;
+        assert_eq!(banded[[0, 0]], 0.0);
+        assert!((banded[[0, 1]] - 0.6).abs() < 1e-3);
+        assert!((banded[[0, 2]] - 1.2).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_magnitude_estimators() {
+        let (r, theta) = (0.95_f32, 0.5_f32);
+        let lpc = lpc_from_poles(&[(r, theta)]).insert_axis(Axis(0));
+        let gain = array![2.0];
+        let magnitude = |estimator| {
+            lpc_coefficients_to_frequencies(
+                lpc.view(),
+                gain.view(),
+                1,
+                &PoleSelectionConfig::default(),
+                estimator,
+                0.0,
+            )
+            .1[[0, 0]]
+        };
+
+        assert!((magnitude(MagnitudeEstimator::PoleGain) - 2.0 / (1.0 - r)).abs() < 1e-2);
+        let envelope = lpc_envelope(lpc.row(0), 2.0, &[theta], 0.0)[0];
+        assert!((magnitude(MagnitudeEstimator::Envelope) - envelope).abs() < 1e-2);
+        let bandwidth_normalized = 2.0 * (2.0 / (1.0 - r)).sqrt();
+        assert!(
+            (magnitude(MagnitudeEstimator::BandwidthNormalized) - bandwidth_normalized).abs()
+                < 1e-2
+        );
+    }
+
+    #[test]
+    fn test_band_energy_magnitudes() {
+        let hop_size = 256;
+        let config = AnalysisConfig {
+            pre_emphasis: 0.0,
+            ..AnalysisConfig::default()
+        };
+        // Two sines, each wave should only see its own
+        let audio = Array1::from_iter(
+            (0..hop_size * 4).map(|i| 0.5 * (0.3 * i as f32).sin() + 0.2 * (1.5 * i as f32).sin()),
+        );
+        let frames = analysis_frames(&audio, hop_size, &config);
+        let frequencies = Array2::from_shape_fn((4, 3), |(_, wave)| [0.3, 1.5, 0.0][wave]);
+
+        let magnitudes = band_energy_magnitudes(
+            frames.view(),
+            config.window(hop_size).view(),
+            frequencies.view(),
+        );
+        // The first and last frames are partially padding
+        for hop in 1..3 {
+            assert!((magnitudes[[hop, 0]] - 0.5).abs() < 0.02);
+            assert!((magnitudes[[hop, 1]] - 0.2).abs() < 0.02);
+            assert_eq!(magnitudes[[hop, 2]], 0.0);
+        }
+    }
+
+    #[test]
+    fn test_warped_lpc() {
+        // Two sines, a warped fit should still find them at their original frequencies
+        let hop_size = 256;
+        let audio = Array1::from_iter(
+            (0..hop_size * 4).map(|i| (0.2 * i as f32).sin() + 0.5 * (0.9 * i as f32).sin()),
+        );
+        let warping = bark_warping(16000);
+        let config = AnalysisConfig {
+            warping,
+            ..AnalysisConfig::default()
+        };
+        let (lpc_coefficients, gain, _residual) = fit_lpc(&audio, 4, hop_size, &config);
+        let (frequencies, _magnitudes) = lpc_coefficients_to_frequencies(
+            lpc_coefficients.view(),
+            gain.view(),
+            2,
+            &PoleSelectionConfig::default(),
+            MagnitudeEstimator::Envelope,
+            warping,
+        );
+
+        assert!((frequencies[[2, 0]] - 0.2).abs() < 0.01);
+        assert!((frequencies[[2, 1]] - 0.9).abs() < 0.01);
+    }
+
     #[test]
     fn test_fit_lpc() {
         // load from msgpack
@@ -191,8 +732,12 @@ mod tests {
         let expected_magnitudes =
             Array2::from_shape_vec((n_steps, input.n_waves), input.magnitudes).unwrap();
 
-        let (lpc_coefficients, gain, residual) =
-            fit_lpc(&audio, input.n_waves * 2, input.hop_size, None);
+        let (lpc_coefficients, gain, residual) = fit_lpc(
+            &audio,
+            input.n_waves * 2,
+            input.hop_size,
+            &AnalysisConfig::default(),
+        );
 
         // Needed to set the epsilon fairly high for this to work, is there
         // concern? Perhaps it's because of float64 in Python or a different
@@ -202,8 +747,14 @@ mod tests {
         assert_array1_eq(&gain, &Array1::from_vec(input.gain), epsilon);
         assert_array1_eq(&residual, &Array1::from_vec(input.residual), epsilon);
 
-        let (frequencies, magnitudes) =
-            lpc_coefficients_to_frequencies(lpc_coefficients.view(), gain.view());
+        let (frequencies, magnitudes) = lpc_coefficients_to_frequencies(
+            lpc_coefficients.view(),
+            gain.view(),
+            input.n_waves,
+            &PoleSelectionConfig::default(),
+            MagnitudeEstimator::PoleGain,
+            0.0,
+        );
 
         assert_array2_eq(&frequencies, &expected_frequencies, epsilon);
         assert_array2_eq(&magnitudes, &expected_magnitudes, epsilon);
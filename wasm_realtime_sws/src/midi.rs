@@ -0,0 +1,466 @@
+use ndarray::{s, ArrayView1, ArrayView2, Axis};
+use thiserror::Error;
+
+use crate::{music::NoteName, tuning::A4_MIDI_NOTE};
+
+/// Ticks per quarter note in the files we write.
+const TICKS_PER_QUARTER_NOTE: u16 = 480;
+/// 120 BPM, meaning a quarter note lasts half a second.
+const MICROSECONDS_PER_QUARTER_NOTE: u32 = 500_000;
+/// The default pitch bend range of most synths.
+const PITCH_BEND_RANGE_SEMITONES: f32 = 2.0;
+/// MIDI channel 10 is reserved for drums in General MIDI, so we skip it.
+const DRUM_CHANNEL: u8 = 9;
+/// Each wave needs its own channel for its pitch bends, and the drum channel is skipped.
+pub const MAX_WAVES: usize = 15;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum MidiExportError {
+    #[error(
+        "MIDI export supports at most {} waves (one channel each), got {0}",
+        MAX_WAVES
+    )]
+    TooManyWaves(usize),
+}
+
+/// Fractional MIDI note number of a frequency in Hz, in 12-TET with A4 = 440 Hz.
+pub fn frequency_to_midi_note(frequency_hz: f32) -> f32 {
+    A4_MIDI_NOTE as f32 + 12.0 * (frequency_hz / 440.0).log2()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoteEvent {
+    /// First and one-past-last frame of the note.
+    pub start_frame: usize,
+    pub end_frame: usize,
+    pub midi_note: u8,
+    pub velocity: u8,
+    /// Deviation from `midi_note` in cents for each frame of the note.
+    pub cents_deviations: Vec<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MidiExportOptions {
+    /// Frames quieter than this (relative to the loudest frame of any wave) are rests.
+    pub silence_threshold_db: f32,
+    /// Velocity 1 corresponds to this many dB below the loudest frame.
+    pub velocity_range_db: f32,
+    /// Emit pitch bend events so that the synth follows the exact frequency,
+    /// not just the nearest note.
+    pub pitch_bend: bool,
+}
+
+impl Default for MidiExportOptions {
+    fn default() -> Self {
+        MidiExportOptions {
+            silence_threshold_db: -40.0,
+            velocity_range_db: 40.0,
+            pitch_bend: false,
+        }
+    }
+}
+
+fn amplitude_to_db(x: f32) -> f32 {
+    20.0 * x.max(1e-10).log10()
+}
+
+/// Split one wave into notes. A new note starts whenever the nearest MIDI note changes
+/// or the wave comes back from silence.
+/// `frequencies_hz` and `magnitudes` are the frames of a single wave.
+pub fn segment_notes(
+    frequencies_hz: ArrayView1<f32>,
+    magnitudes: ArrayView1<f32>,
+    max_magnitude: f32,
+    options: &MidiExportOptions,
+) -> Vec<NoteEvent> {
+    assert_eq!(frequencies_hz.len(), magnitudes.len());
+
+    let mut notes: Vec<NoteEvent> = Vec::new();
+    // (start frame, MIDI note) of the note we're in, if any
+    let mut current: Option<(usize, u8)> = None;
+
+    let frame_note = |i: usize| -> Option<u8> {
+        let db = amplitude_to_db(magnitudes[i]) - amplitude_to_db(max_magnitude);
+        if frequencies_hz[i] <= 0.0 || db < options.silence_threshold_db {
+            return None;
+        }
+        let midi_note = frequency_to_midi_note(frequencies_hz[i]);
+        Some(midi_note.round().clamp(0.0, 127.0) as u8)
+    };
+
+    let mut finish_note = |start_frame: usize, end_frame: usize, midi_note: u8| {
+        let mean_magnitude = magnitudes
+            .slice(s![start_frame..end_frame])
+            .mean()
+            .unwrap_or(0.0);
+        let db = amplitude_to_db(mean_magnitude) - amplitude_to_db(max_magnitude);
+        let velocity = (127.0 * (1.0 + db / options.velocity_range_db)).clamp(1.0, 127.0);
+
+        notes.push(NoteEvent {
+            start_frame,
+            end_frame,
+            midi_note,
+            velocity: velocity.round() as u8,
+            cents_deviations: (start_frame..end_frame)
+                .map(|i| {
+                    let midi_note_exact = frequency_to_midi_note(frequencies_hz[i]);
+                    (midi_note_exact - midi_note as f32) * 100.0
+                })
+                .collect(),
+        });
+    };
+
+    for i in 0..frequencies_hz.len() {
+        let note = frame_note(i);
+        match (current, note) {
+            (Some((_, cur_note)), Some(note)) if cur_note == note => {}
+            (Some((start, cur_note)), _) => {
+                finish_note(start, i, cur_note);
+                current = note.map(|n| (i, n));
+            }
+            (None, _) => current = note.map(|n| (i, n)),
+        }
+    }
+    if let Some((start, cur_note)) = current {
+        finish_note(start, frequencies_hz.len(), cur_note);
+    }
+
+    notes
+}
+
+/// Keeps track of which notes are held down, based on incoming MIDI messages
+/// (e.g. from Web MIDI). Messages from all channels are treated the same.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HeldNotes {
+    /// MIDI note numbers in the order they were pressed.
+    notes: Vec<u8>,
+    /// For running status, where the status byte is omitted if it's the same as before.
+    last_status: Option<u8>,
+}
+
+impl HeldNotes {
+    /// Process raw MIDI bytes, which may contain several messages.
+    pub fn handle_midi_bytes(&mut self, bytes: &[u8]) {
+        let mut i = 0;
+        while i < bytes.len() {
+            let status = if bytes[i] & 0x80 != 0 {
+                i += 1;
+                bytes[i - 1]
+            } else {
+                match self.last_status {
+                    Some(status) => status,
+                    // Data without a status byte to go with it, skip
+                    None => {
+                        i += 1;
+                        continue;
+                    }
+                }
+            };
+
+            if status == 0xf0 {
+                // System exclusive, skip until the end marker
+                while i < bytes.len() && bytes[i] != 0xf7 {
+                    i += 1;
+                }
+                i += 1;
+                continue;
+            }
+
+            let n_data_bytes = match status & 0xf0 {
+                0xc0 | 0xd0 => 1,
+                0x80 | 0x90 | 0xa0 | 0xb0 | 0xe0 => 2,
+                _ => match status {
+                    0xf1 | 0xf3 => 1,
+                    0xf2 => 2,
+                    _ => 0,
+                },
+            };
+            // System common messages cancel running status, real-time ones don't matter
+            if status < 0xf0 {
+                self.last_status = Some(status);
+            } else if status < 0xf8 {
+                self.last_status = None;
+            }
+
+            if i + n_data_bytes > bytes.len() {
+                // Incomplete message
+                return;
+            }
+            let data = &bytes[i..i + n_data_bytes];
+            i += n_data_bytes;
+
+            match (status & 0xf0, data) {
+                // A note-on with zero velocity is a note-off
+                (0x90, [note, velocity]) if *velocity > 0 => self.note_on(*note),
+                (0x80, [note, _]) | (0x90, [note, _]) => self.note_off(*note),
+                // "All sound off" and "All notes off"
+                (0xb0, [120, _]) | (0xb0, [123, _]) => self.notes.clear(),
+                _ => {}
+            }
+        }
+    }
+
+    pub fn note_on(&mut self, note: u8) {
+        self.notes.push(note);
+    }
+
+    pub fn note_off(&mut self, note: u8) {
+        if let Some(position) = self.notes.iter().position(|n| *n == note) {
+            self.notes.remove(position);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.notes.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.notes.is_empty()
+    }
+
+    /// The distinct pitch classes of the held notes, sorted.
+    pub fn pitch_classes(&self) -> Vec<NoteName> {
+        let mut pitch_classes: Vec<NoteName> = self
+            .notes
+            .iter()
+            .map(|note| NoteName::from_pitch_class(*note as i32))
+            .collect();
+        pitch_classes.sort_by_key(|note| note.pitch_class());
+        pitch_classes.dedup();
+        pitch_classes
+    }
+}
+
+/// Variable-length quantity as used for delta times in MIDI files.
+fn write_variable_length(value: u32, out: &mut Vec<u8>) {
+    let mut bytes = vec![(value & 0x7f) as u8];
+    let mut value = value >> 7;
+    while value > 0 {
+        bytes.push(((value & 0x7f) as u8) | 0x80);
+        value >>= 7;
+    }
+    out.extend(bytes.iter().rev());
+}
+
+fn write_chunk(chunk_type: &[u8; 4], data: &[u8], out: &mut Vec<u8>) {
+    out.extend_from_slice(chunk_type);
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(data);
+}
+
+/// Events of one track as (absolute tick, bytes).
+fn write_track(mut events: Vec<(u32, Vec<u8>)>, out: &mut Vec<u8>) {
+    // The sort is stable, so simultaneous events keep their order.
+    events.sort_by_key(|(tick, _)| *tick);
+
+    let mut data = Vec::new();
+    let mut last_tick = 0;
+    for (tick, bytes) in events {
+        write_variable_length(tick - last_tick, &mut data);
+        data.extend(bytes);
+        last_tick = tick;
+    }
+    // End of track
+    data.extend_from_slice(&[0x00, 0xff, 0x2f, 0x00]);
+
+    write_chunk(b"MTrk", &data, out);
+}
+
+fn track_name_event(name: &str) -> Vec<u8> {
+    let mut bytes = vec![0xff, 0x03];
+    write_variable_length(name.len() as u32, &mut bytes);
+    bytes.extend_from_slice(name.as_bytes());
+    bytes
+}
+
+fn pitch_bend_event(channel: u8, cents: f32) -> Vec<u8> {
+    let normalized = (cents / (PITCH_BEND_RANGE_SEMITONES * 100.0)).clamp(-1.0, 1.0);
+    let value = (8192.0 + normalized * 8191.0).round() as u16;
+    vec![0xe0 | channel, (value & 0x7f) as u8, (value >> 7) as u8]
+}
+
+/// Write a format 1 Standard MIDI File with one track and channel per wave, so at most
+/// `MAX_WAVES` waves. Notes are in 12-TET with A4 = 440 Hz, see `frequency_to_midi_note()`.
+///
+/// `frequencies_hz` and `magnitudes` have shape (n_frames, n_waves) and the frames are
+/// `seconds_per_frame` apart.
+pub fn export_midi(
+    frequencies_hz: ArrayView2<f32>,
+    magnitudes: ArrayView2<f32>,
+    seconds_per_frame: f32,
+    options: &MidiExportOptions,
+) -> Result<Vec<u8>, MidiExportError> {
+    assert_eq!(frequencies_hz.shape(), magnitudes.shape());
+    let n_waves = frequencies_hz.len_of(Axis(1));
+    if n_waves > MAX_WAVES {
+        return Err(MidiExportError::TooManyWaves(n_waves));
+    }
+    let max_magnitude = magnitudes.fold(0.0f32, |acc, x| acc.max(*x));
+
+    let ticks_per_second =
+        TICKS_PER_QUARTER_NOTE as f32 * 1e6 / MICROSECONDS_PER_QUARTER_NOTE as f32;
+    let frame_to_tick =
+        |frame: usize| (frame as f32 * seconds_per_frame * ticks_per_second).round() as u32;
+
+    let mut out = Vec::new();
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&1u16.to_be_bytes()); // format 1
+    header.extend_from_slice(&(n_waves as u16 + 1).to_be_bytes());
+    header.extend_from_slice(&TICKS_PER_QUARTER_NOTE.to_be_bytes());
+    write_chunk(b"MThd", &header, &mut out);
+
+    // The first track only sets the tempo
+    let tempo = MICROSECONDS_PER_QUARTER_NOTE.to_be_bytes();
+    write_track(
+        vec![(0, vec![0xff, 0x51, 0x03, tempo[1], tempo[2], tempo[3]])],
+        &mut out,
+    );
+
+    for wave in 0..n_waves {
+        let channel = wave as u8;
+        let channel = if channel >= DRUM_CHANNEL {
+            channel + 1
+        } else {
+            channel
+        };
+
+        let notes = segment_notes(
+            frequencies_hz.slice(s![.., wave]),
+            magnitudes.slice(s![.., wave]),
+            max_magnitude,
+            options,
+        );
+
+        let mut events = vec![(0, track_name_event(&format!("Wave {}", wave + 1)))];
+        for note in notes {
+            let start_tick = frame_to_tick(note.start_frame);
+            if options.pitch_bend {
+                for (i, cents) in note.cents_deviations.iter().enumerate() {
+                    events.push((
+                        frame_to_tick(note.start_frame + i),
+                        pitch_bend_event(channel, *cents),
+                    ));
+                }
+            }
+            events.push((
+                start_tick,
+                vec![0x90 | channel, note.midi_note, note.velocity],
+            ));
+            // Note-offs at the same tick as the next note-on must come first, so we
+            // end the note one tick early when possible.
+            let end_tick = frame_to_tick(note.end_frame).max(start_tick + 2) - 1;
+            events.push((end_tick, vec![0x80 | channel, note.midi_note, 0]));
+        }
+        // Leave the channel unbent for whatever plays after us
+        let last_tick = events.iter().map(|(tick, _)| *tick).max().unwrap_or(0);
+        events.push((last_tick, pitch_bend_event(channel, 0.0)));
+        write_track(events, &mut out);
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::{array, Array2};
+
+    #[test]
+    fn test_write_variable_length() {
+        for (value, expected) in [
+            (0, vec![0x00]),
+            (0x7f, vec![0x7f]),
+            (0x80, vec![0x81, 0x00]),
+            (0x3fff, vec![0xff, 0x7f]),
+            (0x200000, vec![0x81, 0x80, 0x80, 0x00]),
+        ] {
+            let mut out = Vec::new();
+            write_variable_length(value, &mut out);
+            assert_eq!(out, expected);
+        }
+    }
+
+    #[test]
+    fn test_held_notes() {
+        let mut held_notes = HeldNotes::default();
+        // C4 and G4 on, then E5 with running status
+        held_notes.handle_midi_bytes(&[0x90, 60, 100, 0x91, 67, 100, 76, 90]);
+        assert_eq!(
+            held_notes.pitch_classes(),
+            vec![NoteName::C, NoteName::E, NoteName::G]
+        );
+
+        // Note-off, and note-on with zero velocity, which is also a note-off
+        held_notes.handle_midi_bytes(&[0x80, 60, 0]);
+        held_notes.handle_midi_bytes(&[0x90, 67, 0]);
+        assert_eq!(held_notes.pitch_classes(), vec![NoteName::E]);
+
+        // System exclusive messages are skipped
+        held_notes.handle_midi_bytes(&[0xf0, 1, 2, 3, 0xf7, 0x90, 70, 1]);
+        assert_eq!(
+            held_notes.pitch_classes(),
+            vec![NoteName::E, NoteName::ASharp]
+        );
+
+        // All notes off
+        held_notes.handle_midi_bytes(&[0xb0, 123, 0]);
+        assert!(held_notes.is_empty());
+    }
+
+    #[test]
+    fn test_segment_notes() {
+        let frequencies = array![440.0, 445.0, 466.16, 466.16, 466.16, 440.0];
+        let magnitudes = array![1.0, 1.0, 0.5, 0.5, 0.0, 1.0];
+        let notes = segment_notes(
+            frequencies.view(),
+            magnitudes.view(),
+            1.0,
+            &MidiExportOptions::default(),
+        );
+
+        assert_eq!(notes.len(), 3);
+        assert_eq!((notes[0].start_frame, notes[0].end_frame), (0, 2));
+        assert_eq!(notes[0].midi_note, 69);
+        assert_eq!(notes[0].velocity, 127);
+        assert!((notes[0].cents_deviations[1] - 19.56).abs() < 0.1);
+
+        // Frame 4 is silent so the note ends there
+        assert_eq!((notes[1].start_frame, notes[1].end_frame), (2, 4));
+        assert_eq!(notes[1].midi_note, 70);
+        assert!(notes[1].velocity < 127);
+
+        assert_eq!((notes[2].start_frame, notes[2].end_frame), (5, 6));
+    }
+
+    #[test]
+    fn test_export_midi() {
+        let frequencies = array![[440.0, 880.0], [440.0, 880.0]];
+        let magnitudes = array![[1.0, 1.0], [1.0, 1.0]];
+        let options = MidiExportOptions {
+            pitch_bend: true,
+            ..Default::default()
+        };
+        let bytes = export_midi(frequencies.view(), magnitudes.view(), 0.5, &options).unwrap();
+
+        assert_eq!(&bytes[..4], b"MThd");
+        // Format 1, 3 tracks, 480 ticks per quarter note
+        assert_eq!(&bytes[8..14], &[0, 1, 0, 3, 1, 224]);
+        let n_tracks = bytes.windows(4).filter(|w| w == b"MTrk").count();
+        assert_eq!(n_tracks, 3);
+        // Note-on for A5 on the second channel
+        assert!(bytes.windows(3).any(|w| w == [0x91, 81, 127]));
+        // The pitch bend is reset to the centre right before the end of each track
+        for channel in [0xe0, 0xe1] {
+            let reset = [channel, 0x00, 0x40, 0x00, 0xff, 0x2f, 0x00];
+            assert!(bytes.windows(7).any(|w| w == reset));
+        }
+
+        // Not enough channels
+        let frequencies = Array2::from_elem((2, MAX_WAVES + 1), 440.0);
+        assert_eq!(
+            export_midi(frequencies.view(), frequencies.view(), 0.5, &options),
+            Err(MidiExportError::TooManyWaves(MAX_WAVES + 1))
+        );
+    }
+}
@@ -0,0 +1,264 @@
+use crate::{
+    music::{self, FrequencyQuantizationType, Key, NoteName},
+    tuning::Tuning,
+};
+
+/// What one step of a quantization morph snaps to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum QuantizationTarget {
+    Unquantized,
+    /// Resolved against the current key when the morph is applied.
+    Type(FrequencyQuantizationType),
+    Notes(Vec<NoteName>),
+}
+
+impl QuantizationTarget {
+    fn quantize(
+        &self,
+        frequencies: &[f32],
+        key: &Key,
+        tuning: &Tuning,
+        sample_rate: usize,
+    ) -> Vec<f32> {
+        match self {
+            QuantizationTarget::Unquantized => frequencies.to_vec(),
+            QuantizationTarget::Type(quantization_type) => music::quantize_frequencies(
+                frequencies,
+                Some(*quantization_type),
+                key,
+                tuning,
+                sample_rate,
+            ),
+            QuantizationTarget::Notes(notes) => {
+                music::quantize_frequencies_to_notes(frequencies, notes, tuning, sample_rate)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MorphTarget {
+    pub target: QuantizationTarget,
+    /// (morph position, weight) points sorted by position. The weight is interpolated
+    /// linearly between the points and kept constant before the first and after the last one.
+    pub weight_curve: Vec<(f32, f32)>,
+}
+
+/// Morphs between several quantizations of the same frequencies. Each target has a weight
+/// that depends on the morph position, and the result is the weighted average of the
+/// quantized versions in the cents domain (i.e. a weighted geometric mean).
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuantizationMorph {
+    pub targets: Vec<MorphTarget>,
+}
+
+impl QuantizationMorph {
+    /// The original 0-3 quantization strength slider: unquantized, then chromatic, diatonic
+    /// and pentatonic. The weights are handcrafted so that the unquantized version fades out
+    /// slowly over the whole range and the others peak at 1, 2 and 3 respectively.
+    pub fn strength_preset() -> QuantizationMorph {
+        let target = |target: QuantizationTarget, weight_curve: &[(f32, f32)]| MorphTarget {
+            target,
+            weight_curve: weight_curve.to_vec(),
+        };
+        QuantizationMorph {
+            targets: vec![
+                target(QuantizationTarget::Unquantized, &[(0.0, 1.0), (3.0, 0.0)]),
+                target(
+                    QuantizationTarget::Type(FrequencyQuantizationType::Chromatic),
+                    &[(0.0, 0.0), (1.0, 3.0), (2.0, 0.0)],
+                ),
+                target(
+                    QuantizationTarget::Type(FrequencyQuantizationType::Diatonic),
+                    &[(1.0, 0.0), (2.0, 3.0), (3.0, 0.0)],
+                ),
+                target(
+                    QuantizationTarget::Type(FrequencyQuantizationType::Pentatonic),
+                    &[(2.0, 0.0), (3.0, 3.0)],
+                ),
+            ],
+        }
+    }
+
+    /// Normalized weights of the targets at the given position. The position is clamped to
+    /// the range where some target has a positive weight, so that e.g. with the default
+    /// triangular curves, positions past the last target give the last target.
+    /// Empty if there are no targets.
+    pub fn weights(&self, position: f32) -> Vec<f32> {
+        if self.targets.is_empty() {
+            return Vec::new();
+        }
+        let position = match self.position_range() {
+            Some((min, max)) => position.clamp(min, max),
+            None => position,
+        };
+
+        let weights: Vec<f32> = self
+            .targets
+            .iter()
+            .map(|t| interpolate_clamped(&t.weight_curve, position).max(0.0))
+            .collect();
+        let total_weight: f32 = weights.iter().sum();
+        if total_weight <= 0.0 {
+            panic!("all morph weights are zero at position {}", position);
+        }
+        weights.iter().map(|w| w / total_weight).collect()
+    }
+
+    pub fn apply(
+        &self,
+        frequencies: &[f32],
+        position: f32,
+        key: &Key,
+        tuning: &Tuning,
+        sample_rate: usize,
+    ) -> Vec<f32> {
+        if self.targets.is_empty() {
+            return frequencies.to_vec();
+        }
+        let weights = self.weights(position);
+
+        let mut log_result = vec![0.0; frequencies.len()];
+        for (target, weight) in self.targets.iter().zip(weights) {
+            // No need to compute versions that don't contribute
+            if weight == 0.0 {
+                continue;
+            }
+            let quantized = target
+                .target
+                .quantize(frequencies, key, tuning, sample_rate);
+            for (result, quantized) in log_result.iter_mut().zip(quantized) {
+                *result += quantized.ln() * weight;
+            }
+        }
+
+        frequencies
+            .iter()
+            .zip(log_result)
+            .map(|(original, log_result)| {
+                // Keep missing formants at 0 instead of producing NaNs
+                if *original <= 0.0 {
+                    *original
+                } else {
+                    log_result.exp()
+                }
+            })
+            .collect()
+    }
+
+    /// The lowest and highest positions where some target has a positive weight.
+    fn position_range(&self) -> Option<(f32, f32)> {
+        self.targets
+            .iter()
+            .flat_map(|t| t.weight_curve.iter())
+            .filter(|(_, weight)| *weight > 0.0)
+            .fold(None, |range, &(position, _)| match range {
+                None => Some((position, position)),
+                Some((min, max)) => Some((position.min(min), position.max(max))),
+            })
+    }
+}
+
+/// Weight 1 at position `i`, falling to 0 at `i - 1` and `i + 1`.
+pub fn triangular_weight_curve(i: usize) -> Vec<(f32, f32)> {
+    let i = i as f32;
+    vec![(i - 1.0, 0.0), (i, 1.0), (i + 1.0, 0.0)]
+}
+
+/// Parse a flattened [position_0, weight_0, position_1, weight_1, ...] curve as passed from JS.
+pub fn weight_curve_from_flat(flat: &[f32]) -> Vec<(f32, f32)> {
+    if flat.len() % 2 != 0 {
+        panic!(
+            "weight curve must have an even number of values, got {}",
+            flat.len()
+        );
+    }
+    let mut curve: Vec<(f32, f32)> = flat.chunks(2).map(|c| (c[0], c[1])).collect();
+    curve.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    curve
+}
+
+/// Piecewise-linear interpolation that is constant outside of the given points.
+fn interpolate_clamped(points: &[(f32, f32)], x: f32) -> f32 {
+    match (points.first(), points.last()) {
+        (None, _) | (_, None) => 0.0,
+        (Some(first), _) if x <= first.0 => first.1,
+        (_, Some(last)) if x >= last.0 => last.1,
+        _ => {
+            let segment = points.windows(2).find(|w| x < w[1].0).unwrap();
+            let (x0, y0) = segment[0];
+            let (x1, y1) = segment[1];
+            y0 + (x - x0) * (y1 - y0) / (x1 - x0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strength_preset_weights() {
+        let morph = QuantizationMorph::strength_preset();
+        assert_eq!(morph.weights(0.0), vec![1.0, 0.0, 0.0, 0.0]);
+
+        // Same as the original handcrafted weights: at 1.0 the unquantized version has
+        // weight 2/3 and chromatic has weight 3.
+        let weights = morph.weights(1.0);
+        assert!((weights[0] - (2. / 3.) / (2. / 3. + 3.)).abs() < 1e-6);
+        assert_eq!(weights[2], 0.0);
+
+        let weights = morph.weights(3.0);
+        assert_eq!(weights, vec![0.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_morph_in_cents_domain() {
+        let sample_rate = 8000;
+        let to_normalized = (2. * std::f32::consts::PI) / sample_rate as f32;
+        let morph = QuantizationMorph {
+            targets: vec![
+                MorphTarget {
+                    target: QuantizationTarget::Notes(vec![NoteName::A]),
+                    weight_curve: triangular_weight_curve(0),
+                },
+                MorphTarget {
+                    target: QuantizationTarget::Notes(vec![NoteName::C]),
+                    weight_curve: triangular_weight_curve(1),
+                },
+            ],
+        };
+        let frequencies = vec![450. * to_normalized, 0.0];
+
+        let result = morph.apply(
+            &frequencies,
+            0.5,
+            &Key::default(),
+            &Tuning::default(),
+            sample_rate,
+        );
+        // Halfway between A4 and C5 in cents is 150 cents above A4
+        let expected_hz = 440. * 2.0f32.powf(1.5 / 12.);
+        assert!((result[0] / to_normalized - expected_hz).abs() < 1e-2);
+        assert_eq!(result[1], 0.0);
+    }
+
+    #[test]
+    fn test_morph_out_of_range() {
+        let target = |i| MorphTarget {
+            target: QuantizationTarget::Unquantized,
+            weight_curve: triangular_weight_curve(i),
+        };
+        let morph = QuantizationMorph {
+            targets: vec![target(0), target(1)],
+        };
+        assert_eq!(morph.weights(5.0), vec![0.0, 1.0]);
+        assert_eq!(morph.weights(-2.0), vec![1.0, 0.0]);
+
+        let empty = QuantizationMorph { targets: vec![] };
+        assert!(empty.weights(1.0).is_empty());
+        let frequencies = vec![0.1, 0.2];
+        let result = empty.apply(&frequencies, 1.0, &Key::default(), &Tuning::default(), 8000);
+        assert_eq!(result, frequencies);
+    }
+}
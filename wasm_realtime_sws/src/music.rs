@@ -1,5 +1,8 @@
 use wasm_bindgen::prelude::wasm_bindgen;
 
+use crate::tuning::Tuning;
+
+#[wasm_bindgen]
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum NoteName {
     C,
@@ -31,24 +34,107 @@ pub const CHROMATIC: &[NoteName] = &[
     NoteName::B,
 ];
 
-// C major is a diatonic scale
-pub const C_MAJOR: &[NoteName] = &[
-    NoteName::C,
-    NoteName::D,
-    NoteName::E,
-    NoteName::F,
-    NoteName::G,
-    NoteName::A,
-    NoteName::B,
-];
+#[wasm_bindgen]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, serde::Deserialize)]
+pub enum ScaleMode {
+    Major,
+    NaturalMinor,
+    HarmonicMinor,
+    MelodicMinor,
+    Dorian,
+    Phrygian,
+    Lydian,
+    Mixolydian,
+    Locrian,
+    MajorPentatonic,
+    MinorPentatonic,
+    Blues,
+    WholeTone,
+}
 
-pub const C_MAJOR_PENTATONIC: &[NoteName] = &[
-    NoteName::C,
-    NoteName::D,
-    NoteName::E,
-    NoteName::G,
-    NoteName::A,
-];
+impl ScaleMode {
+    /// Semitones above the root.
+    pub fn intervals(self) -> &'static [u8] {
+        match self {
+            ScaleMode::Major => &[0, 2, 4, 5, 7, 9, 11],
+            ScaleMode::NaturalMinor => &[0, 2, 3, 5, 7, 8, 10],
+            ScaleMode::HarmonicMinor => &[0, 2, 3, 5, 7, 8, 11],
+            // The ascending version, which is what is usually meant in jazz
+            ScaleMode::MelodicMinor => &[0, 2, 3, 5, 7, 9, 11],
+            ScaleMode::Dorian => &[0, 2, 3, 5, 7, 9, 10],
+            ScaleMode::Phrygian => &[0, 1, 3, 5, 7, 8, 10],
+            ScaleMode::Lydian => &[0, 2, 4, 6, 7, 9, 11],
+            ScaleMode::Mixolydian => &[0, 2, 4, 5, 7, 9, 10],
+            ScaleMode::Locrian => &[0, 1, 3, 5, 6, 8, 10],
+            ScaleMode::MajorPentatonic => &[0, 2, 4, 7, 9],
+            ScaleMode::MinorPentatonic => &[0, 3, 5, 7, 10],
+            ScaleMode::Blues => &[0, 3, 5, 6, 7, 10],
+            ScaleMode::WholeTone => &[0, 2, 4, 6, 8, 10],
+        }
+    }
+}
+
+/// A root note together with the intervals of the scale built on it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Key {
+    pub root: NoteName,
+    /// Semitones above the root, each in 0..12.
+    pub intervals: Vec<u8>,
+}
+
+impl Default for Key {
+    fn default() -> Self {
+        Key::new(NoteName::C, ScaleMode::Major)
+    }
+}
+
+impl Key {
+    pub fn new(root: NoteName, mode: ScaleMode) -> Key {
+        Key {
+            root,
+            intervals: mode.intervals().to_vec(),
+        }
+    }
+
+    /// A key from a user-supplied set of intervals (semitones above the root).
+    /// Intervals outside of 0..12 are wrapped around to the same pitch class.
+    pub fn custom(root: NoteName, intervals: &[i32]) -> Key {
+        let mut intervals: Vec<u8> = intervals.iter().map(|i| i.rem_euclid(12) as u8).collect();
+        intervals.sort();
+        intervals.dedup();
+        if intervals.is_empty() {
+            panic!("a custom key needs at least one interval");
+        }
+        Key { root, intervals }
+    }
+
+    pub fn scale(&self) -> Vec<NoteName> {
+        self.intervals
+            .iter()
+            .map(|i| self.root.transpose(*i as i32))
+            .collect()
+    }
+
+    /// A five-note subset of the scale, used for the strongest quantization.
+    /// We take the major pentatonic if the scale has a major third and the minor pentatonic
+    /// otherwise, keeping only notes that are in the scale. Scales with at most five notes
+    /// are returned unchanged.
+    pub fn pentatonic_scale(&self) -> Vec<NoteName> {
+        if self.intervals.len() <= 5 {
+            return self.scale();
+        }
+        let pentatonic = if self.intervals.contains(&4) {
+            ScaleMode::MajorPentatonic
+        } else {
+            ScaleMode::MinorPentatonic
+        };
+        self.intervals
+            .iter()
+            .filter(|i| pentatonic.intervals().contains(i))
+            .map(|i| self.root.transpose(*i as i32))
+            .collect()
+    }
+}
 
 #[wasm_bindgen]
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -59,36 +145,36 @@ pub enum FrequencyQuantizationType {
 }
 
 impl FrequencyQuantizationType {
-    pub fn to_scale(&self) -> &'static [NoteName] {
+    /// The notes to snap to. "Diatonic" is a slight misnomer now since the key
+    /// can be any scale, not only a seven-note one.
+    pub fn to_scale(self, key: &Key) -> Vec<NoteName> {
         match self {
-            FrequencyQuantizationType::Chromatic => CHROMATIC,
-            FrequencyQuantizationType::Diatonic => C_MAJOR,
-            FrequencyQuantizationType::Pentatonic => C_MAJOR_PENTATONIC,
+            FrequencyQuantizationType::Chromatic => CHROMATIC.to_vec(),
+            FrequencyQuantizationType::Diatonic => key.scale(),
+            FrequencyQuantizationType::Pentatonic => key.pentatonic_scale(),
         }
     }
 }
 
 impl NoteName {
-    fn octave_4_frequency(&self) -> f32 {
-        // https://mixbutton.com/mixing-articles/music-note-to-frequency-chart/
-        match self {
-            NoteName::C => 261.63,
-            NoteName::CSharp => 277.18,
-            NoteName::D => 293.66,
-            NoteName::DSharp => 311.13,
-            NoteName::E => 329.63,
-            NoteName::F => 349.23,
-            NoteName::FSharp => 369.99,
-            NoteName::G => 392.00,
-            NoteName::GSharp => 415.30,
-            NoteName::A => 440.00,
-            NoteName::ASharp => 466.16,
-            NoteName::B => 493.88,
-        }
+    /// 0 for C, 1 for C# etc.
+    pub fn pitch_class(self) -> u8 {
+        self as u8
     }
 
-    fn to_string(&self) -> String {
-        match self {
+    /// Pitch classes outside of 0..12 wrap around.
+    pub fn from_pitch_class(pitch_class: i32) -> NoteName {
+        CHROMATIC[pitch_class.rem_euclid(12) as usize]
+    }
+
+    pub fn transpose(self, semitones: i32) -> NoteName {
+        NoteName::from_pitch_class(self.pitch_class() as i32 + semitones)
+    }
+}
+
+impl std::fmt::Display for NoteName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
             NoteName::C => "C",
             NoteName::CSharp => "C#",
             NoteName::D => "D",
@@ -101,33 +187,59 @@ impl NoteName {
             NoteName::A => "A",
             NoteName::ASharp => "A#",
             NoteName::B => "B",
-        }
-        .to_string()
+        };
+        write!(f, "{}", name)
     }
 }
 
+/// Frequencies (in Hz, unless `frequency_multiplier` is given) of the given notes
+/// in the given octaves, sorted.
+///
+/// Note names only make sense for 12-tone tunings. For other tunings, we return
+/// all degrees of the tuning that fall into the same frequency range instead.
 pub fn generate_scale(
-    note_names: &Vec<NoteName>,
+    note_names: &[NoteName],
     start_octave: i32,
     end_octave: i32,
+    tuning: &Tuning,
     // Useful if you are working with normalized frequencies (radian/sample)
     // rather than Hz
     frequency_multiplier: Option<f32>,
 ) -> Vec<f32> {
     let frequency_multiplier = frequency_multiplier.unwrap_or(1.0);
-    let mut scale = Vec::new();
-    for octave in start_octave..=end_octave {
-        for note in note_names {
-            scale.push(note.octave_4_frequency() * 2.0f32.powi(octave - 4) * frequency_multiplier);
-        }
-    }
+    // MIDI note 0 is C-1
+    let first_note = (start_octave + 1) * 12;
+    let last_note = (end_octave + 2) * 12 - 1;
+
+    let mut scale: Vec<f32> = if tuning.is_twelve_tone() {
+        (first_note..=last_note)
+            .filter(|note| note_names.contains(&NoteName::from_pitch_class(*note)))
+            .filter_map(|note| tuning.frequency(note))
+            .collect()
+    } else {
+        let equal = Tuning::default();
+        let min_frequency = equal.frequency(first_note).unwrap();
+        let max_frequency = equal.frequency(last_note).unwrap();
+
+        // Find the notes in the range, making sure to include all notes even
+        // for tunings with a lot of notes per octave.
+        let notes_per_octave = tuning.n_degrees().max(12) as i32;
+        let margin = notes_per_octave * (end_octave - start_octave + 2);
+        (tuning.middle_note - margin * 2..=tuning.middle_note + margin * 2)
+            .filter_map(|note| tuning.frequency(note))
+            .filter(|f| *f >= min_frequency && *f <= max_frequency)
+            .collect()
+    };
+
+    scale.iter_mut().for_each(|f| *f *= frequency_multiplier);
     scale.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    scale.dedup();
     scale
 }
 
 /// Snaps a frequency to the closest note in the allowed frequencies.
-pub fn quantize_frequency(to_snap: f32, allowed_frequencies: &Vec<f32>) -> f32 {
-    let mut min_diff_cents = std::f32::MAX;
+pub fn quantize_frequency(to_snap: f32, allowed_frequencies: &[f32]) -> f32 {
+    let mut min_diff_cents = f32::MAX;
     let mut closest_note = 0.0;
     for note in allowed_frequencies {
         let diff_cents = (1200.0 * (to_snap / note).log2()).abs();
@@ -140,37 +252,199 @@ pub fn quantize_frequency(to_snap: f32, allowed_frequencies: &Vec<f32>) -> f32 {
 }
 
 pub fn quantize_frequencies(
-    frequencies: &Vec<f32>,
+    frequencies: &[f32],
     quantization_type: Option<FrequencyQuantizationType>,
+    key: &Key,
+    tuning: &Tuning,
     sample_rate: usize,
 ) -> Vec<f32> {
     match quantization_type {
-        None => return frequencies.clone(),
-        Some(quantization_type) => {
-            let allowed_notes = quantization_type.to_scale();
-
-            const MIN_OCTAVE: i32 = 0;
-            const MAX_OCTAVE: i32 = 8;
-            let frequency_multiplier: f32 = (2. * std::f32::consts::PI) / sample_rate as f32;
-            let allowed_frequencies = generate_scale(
-                &allowed_notes.to_vec(),
-                MIN_OCTAVE,
-                MAX_OCTAVE,
-                Some(frequency_multiplier),
-            );
-
-            let quantized_frequencies = frequencies
-                .iter()
-                .map(|x| quantize_frequency(*x, &allowed_frequencies))
-                .collect();
-            quantized_frequencies
+        None => frequencies.to_vec(),
+        Some(quantization_type) => quantize_frequencies_to_notes(
+            frequencies,
+            &quantization_type.to_scale(key),
+            tuning,
+            sample_rate,
+        ),
+    }
+}
+
+/// Normalized frequencies of the given notes in all octaves we quantize to, sorted.
+pub fn allowed_frequencies(
+    allowed_notes: &[NoteName],
+    tuning: &Tuning,
+    sample_rate: usize,
+) -> Vec<f32> {
+    const MIN_OCTAVE: i32 = 0;
+    const MAX_OCTAVE: i32 = 8;
+    let frequency_multiplier: f32 = (2. * std::f32::consts::PI) / sample_rate as f32;
+    generate_scale(
+        allowed_notes,
+        MIN_OCTAVE,
+        MAX_OCTAVE,
+        tuning,
+        Some(frequency_multiplier),
+    )
+}
+
+/// Snaps normalized frequencies to the given notes in any octave.
+pub fn quantize_frequencies_to_notes(
+    frequencies: &[f32],
+    allowed_notes: &[NoteName],
+    tuning: &Tuning,
+    sample_rate: usize,
+) -> Vec<f32> {
+    let allowed_frequencies = allowed_frequencies(allowed_notes, tuning, sample_rate);
+
+    frequencies
+        .iter()
+        .map(|x| quantize_frequency(*x, &allowed_frequencies))
+        .collect()
+}
+
+/// `n_points` frequencies from `min` to `max` (inclusive), evenly spaced in cents.
+pub fn log_spaced_frequencies(min: f32, max: f32, n_points: usize) -> Vec<f32> {
+    assert!(
+        0.0 < min && min <= max,
+        "expected 0 < min <= max, got {} and {}",
+        min,
+        max
+    );
+    if n_points == 1 {
+        return vec![min];
+    }
+    let ratio = max / min;
+    (0..n_points)
+        .map(|i| min * ratio.powf(i as f32 / (n_points - 1) as f32))
+        .collect()
+}
+
+fn abs_cents(a: f32, b: f32) -> f32 {
+    (1200.0 * (a / b).log2()).abs()
+}
+
+/// What a tuner would show for one wave.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoteAnalysis {
+    /// Index of the wave within the frame.
+    pub wave: usize,
+    pub frequency_hz: f32,
+    /// The nearest note of the tuning.
+    pub midi_note: i32,
+    pub note_name: NoteName,
+    /// Octave in scientific pitch notation, so MIDI note 60 is C4.
+    pub octave: i32,
+    /// Positive if the frequency is above the nearest note.
+    pub cents_offset: f32,
+}
+
+#[wasm_bindgen]
+impl NoteAnalysis {
+    /// The note name with the octave, e.g. "C#4".
+    pub fn label(&self) -> String {
+        format!("{}{}", self.note_name, self.octave)
+    }
+}
+
+/// Find the nearest note of the tuning to a frequency in Hz. Returns `None` for missing
+/// formants (frequency 0). Note names assume the usual mapping of MIDI notes to pitch
+/// classes, so they're only meaningful for 12-tone tunings.
+pub fn analyze_note(wave: usize, frequency_hz: f32, tuning: &Tuning) -> Option<NoteAnalysis> {
+    if frequency_hz <= 0.0 {
+        return None;
+    }
+    let (midi_note, note_frequency) = (0..128)
+        .filter_map(|midi_note| Some((midi_note, tuning.frequency(midi_note)?)))
+        .min_by(|(_, a), (_, b)| {
+            abs_cents(frequency_hz, *a)
+                .partial_cmp(&abs_cents(frequency_hz, *b))
+                .unwrap()
+        })?;
+
+    Some(NoteAnalysis {
+        wave,
+        frequency_hz,
+        midi_note,
+        note_name: NoteName::from_pitch_class(midi_note),
+        octave: midi_note.div_euclid(12) - 1,
+        cents_offset: 1200.0 * (frequency_hz / note_frequency).log2(),
+    })
+}
+
+/// Quantizes the waves of one frame so that no two waves get the same note.
+///
+/// The waves keep their order (the lowest wave gets the lowest note etc.) and we minimize
+/// the total deviation in cents. If `previous_notes` are given (one per wave), moving away
+/// from them costs an extra `voice_leading_weight` per cent, which favors small movements.
+/// Frequencies of 0 (no formant) are left alone. If there are more waves than notes,
+/// we fall back to quantizing each wave independently.
+pub fn quantize_frequencies_distinct(
+    frequencies: &[f32],
+    allowed_frequencies: &[f32],
+    previous_notes: Option<&[f32]>,
+    voice_leading_weight: f32,
+) -> Vec<f32> {
+    // Indices of the waves to assign, sorted by frequency
+    let mut waves: Vec<usize> = (0..frequencies.len())
+        .filter(|i| frequencies[*i] > 0.0)
+        .collect();
+    waves.sort_by(|a, b| frequencies[*a].partial_cmp(&frequencies[*b]).unwrap());
+
+    let n_notes = allowed_frequencies.len();
+    if waves.len() > n_notes {
+        return frequencies
+            .iter()
+            .map(|x| quantize_frequency(*x, allowed_frequencies))
+            .collect();
+    }
+
+    let cost = |wave: usize, note: usize| -> f32 {
+        let mut cost = abs_cents(frequencies[wave], allowed_frequencies[note]);
+        if let Some(previous) = previous_notes.and_then(|p| p.get(wave)) {
+            if *previous > 0.0 {
+                cost += voice_leading_weight * abs_cents(*previous, allowed_frequencies[note]);
+            }
+        }
+        cost
+    };
+
+    // best[k][j]: lowest total cost of assigning the first k+1 waves
+    // such that wave k gets note j. parent[k][j] is the note of wave k-1 in that case.
+    let mut best = vec![vec![f32::INFINITY; n_notes]; waves.len()];
+    let mut parent = vec![vec![0; n_notes]; waves.len()];
+    for (k, wave) in waves.iter().enumerate() {
+        // Lowest cost (and its note) for the previous wave among notes below j
+        let mut prefix_min = (f32::INFINITY, 0);
+        for j in 0..n_notes {
+            if k == 0 {
+                best[k][j] = cost(*wave, j);
+                continue;
+            }
+            if j > 0 && best[k - 1][j - 1] < prefix_min.0 {
+                prefix_min = (best[k - 1][j - 1], j - 1);
+            }
+            best[k][j] = prefix_min.0 + cost(*wave, j);
+            parent[k][j] = prefix_min.1;
         }
     }
+
+    let mut result = frequencies.to_vec();
+    if let Some(last) = best.last() {
+        let mut note = (0..n_notes)
+            .min_by(|a, b| last[*a].partial_cmp(&last[*b]).unwrap())
+            .unwrap();
+        for k in (0..waves.len()).rev() {
+            result[waves[k]] = allowed_frequencies[note];
+            note = parent[k][note];
+        }
+    }
+    result
 }
 
-pub fn add_depth(frequencies: &Vec<f32>, width: f32) -> Vec<f32> {
+pub fn add_depth(frequencies: &[f32], width: f32) -> Vec<f32> {
     if frequencies.len() <= 1 {
-        return frequencies.clone();
+        return frequencies.to_vec();
     }
     frequencies
         .iter()
@@ -185,26 +459,205 @@ pub fn add_depth(frequencies: &Vec<f32>, width: f32) -> Vec<f32> {
         .collect()
 }
 
+/// Moves a frequency by octaves so that it lands in [low, high].
+/// If the range is narrower than an octave, this might not be possible,
+/// in which case we take the octave that's closest to the range (in cents).
+pub fn fold_into_range(frequency: f32, low: f32, high: f32) -> f32 {
+    if frequency <= 0.0 {
+        return frequency;
+    }
+    // The octave of `frequency` in [low, 2 * low)
+    let folded = frequency * 2.0f32.powf((low / frequency).log2().ceil());
+    if folded <= high {
+        return folded;
+    }
+    // Too high, but the octave below might be closer
+    if abs_cents(folded, high) <= abs_cents(folded / 2.0, low) {
+        folded
+    } else {
+        folded / 2.0
+    }
+}
+
+/// Parse a flattened [low_0, high_0, low_1, high_1, ...] list of ranges as passed from JS.
+pub fn ranges_from_flat(flat: &[f32]) -> Vec<(f32, f32)> {
+    if flat.len() % 2 != 0 {
+        panic!(
+            "ranges must have an even number of values, got {}",
+            flat.len()
+        );
+    }
+    flat.chunks(2)
+        .map(|c| {
+            if !(0.0 < c[0] && c[0] < c[1]) {
+                panic!(
+                    "invalid range [{}, {}], expected 0 < low < high",
+                    c[0], c[1]
+                );
+            }
+            (c[0], c[1])
+        })
+        .collect()
+}
+
+/// Folds the waves of one frame into a range per wave, keeping their pitch classes.
+/// Waves without a range are left alone. If the ranges overlap, folding could change
+/// the order of the waves, so waves that end up below the previous one are moved
+/// up by octaves.
+pub fn fold_registers(frequencies: &[f32], ranges: &[(f32, f32)]) -> Vec<f32> {
+    let mut result: Vec<f32> = frequencies
+        .iter()
+        .enumerate()
+        .map(|(i, f)| match ranges.get(i) {
+            Some((low, high)) => fold_into_range(*f, *low, *high),
+            None => *f,
+        })
+        .collect();
+
+    let mut previous = 0.0;
+    for f in result.iter_mut().take(ranges.len()) {
+        if *f <= 0.0 {
+            continue;
+        }
+        while *f < previous {
+            *f *= 2.0;
+        }
+        previous = *f;
+    }
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     #[test]
     fn test_generate_scale() {
-        let scale: Vec<f32> =
-            generate_scale(&vec![NoteName::C, NoteName::E, NoteName::G], 2, 3, None)
-                .iter()
-                .map(|x| (x * 100.).round() / 100.)
-                .collect();
+        let scale: Vec<f32> = generate_scale(
+            &[NoteName::C, NoteName::E, NoteName::G],
+            2,
+            3,
+            &Tuning::default(),
+            None,
+        )
+        .iter()
+        .map(|x| (x * 100.).round() / 100.)
+        .collect();
 
         assert_eq!(scale.len(), 6);
         assert_eq!(scale[0], 65.41);
         assert_eq!(scale[1], 82.41);
         assert_eq!(scale[2], 98.00);
-        assert_eq!(scale[3], 130.82);
-        assert_eq!(scale[4], 164.82);
+        assert_eq!(scale[3], 130.81);
+        assert_eq!(scale[4], 164.81);
         assert_eq!(scale[5], 196.00);
     }
 
+    #[test]
+    fn test_key() {
+        let key = Key::new(NoteName::A, ScaleMode::NaturalMinor);
+        assert_eq!(
+            key.scale(),
+            vec![
+                NoteName::A,
+                NoteName::B,
+                NoteName::C,
+                NoteName::D,
+                NoteName::E,
+                NoteName::F,
+                NoteName::G
+            ]
+        );
+        // No major third, so we get the minor pentatonic
+        assert_eq!(
+            key.pentatonic_scale(),
+            vec![
+                NoteName::A,
+                NoteName::C,
+                NoteName::D,
+                NoteName::E,
+                NoteName::G
+            ]
+        );
+
+        let key = Key::new(NoteName::D, ScaleMode::Major);
+        assert_eq!(key.pentatonic_scale()[2], NoteName::FSharp);
+
+        let key = Key::custom(NoteName::E, &[0, 7, 19, -5]);
+        assert_eq!(key.intervals, vec![0, 7]);
+        assert_eq!(key.scale(), vec![NoteName::E, NoteName::B]);
+    }
+
+    #[test]
+    fn test_quantize_frequencies_distinct() {
+        let notes = vec![100., 200., 300., 400.];
+
+        // Independently, both would go to 200
+        let quantized = quantize_frequencies_distinct(&[190., 210.], &notes, None, 0.0);
+        assert_eq!(quantized, vec![200., 300.]);
+        let quantized = quantize_frequencies_distinct(&[190., 160.], &notes, None, 0.0);
+        assert_eq!(quantized, vec![200., 100.]);
+
+        // Missing formants are left alone
+        let quantized = quantize_frequencies_distinct(&[0., 190., 210.], &notes, None, 0.0);
+        assert_eq!(quantized, vec![0., 200., 300.]);
+
+        // Voice leading prefers staying on the previous notes
+        let previous = [100., 200.];
+        let quantized = quantize_frequencies_distinct(&[190., 210.], &notes, Some(&previous), 1.0);
+        assert_eq!(quantized, vec![100., 200.]);
+
+        // Too many waves for the notes
+        let quantized = quantize_frequencies_distinct(&[190., 210.], &[200.], None, 0.0);
+        assert_eq!(quantized, vec![200., 200.]);
+    }
+
+    #[test]
+    fn test_fold_registers() {
+        assert_eq!(fold_into_range(100., 130., 260.), 200.);
+        assert_eq!(fold_into_range(1000., 130., 260.), 250.);
+        assert_eq!(fold_into_range(0., 130., 260.), 0.);
+        // Narrow range, 300 and 150 are both outside of [160, 250],
+        // but 150 is closer
+        assert_eq!(fold_into_range(600., 160., 250.), 150.);
+
+        let ranges = ranges_from_flat(&[130., 260., 260., 520.]);
+        assert_eq!(ranges, vec![(130., 260.), (260., 520.)]);
+        assert_eq!(
+            fold_registers(&[100., 1000., 50.], &ranges),
+            vec![200., 500., 50.]
+        );
+
+        // Overlapping ranges, the second wave would end up below the first one
+        let ranges = [(200., 400.), (200., 400.)];
+        assert_eq!(fold_registers(&[300., 500.], &ranges), vec![300., 500.]);
+    }
+
+    #[test]
+    fn test_log_spaced_frequencies() {
+        let frequencies = log_spaced_frequencies(110.0, 440.0, 5);
+        let expected = [110.0, 155.56, 220.0, 311.13, 440.0];
+        for (frequency, expected) in frequencies.iter().zip(expected) {
+            assert!((frequency - expected).abs() < 0.01);
+        }
+        assert_eq!(log_spaced_frequencies(110.0, 440.0, 1), vec![110.0]);
+        assert!(log_spaced_frequencies(110.0, 440.0, 0).is_empty());
+    }
+
+    #[test]
+    fn test_analyze_note() {
+        let tuning = Tuning::default();
+        let analysis = analyze_note(1, 445.0, &tuning).unwrap();
+        assert_eq!(analysis.midi_note, 69);
+        assert_eq!(analysis.label(), "A4");
+        assert!((analysis.cents_offset - 19.56).abs() < 0.01);
+
+        let analysis = analyze_note(0, 270.0, &tuning).unwrap();
+        assert_eq!(analysis.label(), "C#4");
+        assert!(analysis.cents_offset < 0.0);
+
+        assert_eq!(analyze_note(0, 0.0, &tuning), None);
+    }
+
     #[test]
     fn test_quantize_frequency() {
         let notes = vec![100., 200., 300.];
@@ -0,0 +1,247 @@
+use ndarray::prelude::*;
+
+use crate::lpc::{fit_frame, AnalysisConfig};
+use crate::signal_processing::lfilter;
+
+/// The centre of one analysis window. For voiced audio it is a glottal epoch and the window
+/// spans two pitch periods, for unvoiced audio it is the centre of a hop and the window is
+/// the usual one of the config.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Epoch {
+    pub position: usize,
+    pub period: Option<f32>,
+}
+
+/// Place epochs one pitch period apart, each snapped to the strongest sample near where
+/// the previous period predicts it, which is roughly the glottal closure. `periods` has
+/// one F0 estimate per hop, None for unvoiced hops, which get one epoch at their centre
+/// like in `resample_tracks()`.
+pub fn find_epochs(audio: ArrayView1<f32>, periods: &[Option<f32>], hop_size: usize) -> Vec<Epoch> {
+    let mut epochs: Vec<Epoch> = Vec::new();
+    let mut position = 0;
+
+    while position < audio.len() && !periods.is_empty() {
+        let hop = position / hop_size;
+        let period = match periods[hop.min(periods.len() - 1)] {
+            Some(period) => period,
+            None => {
+                // Voiced epochs may already have passed the centre
+                let centre = hop * hop_size + hop_size / 2;
+                epochs.push(Epoch {
+                    position: centre.max(position).min(audio.len() - 1),
+                    period: None,
+                });
+                position = (hop + 1) * hop_size;
+                continue;
+            }
+        };
+
+        // After unvoiced audio, the first epoch can be anywhere in the next period
+        let (start, end) = match epochs.last() {
+            Some(Epoch {
+                position: previous,
+                period: Some(_),
+            }) => (
+                (position as f32 - period / 4.0).max(*previous as f32 + 1.0) as usize,
+                (position as f32 + period / 4.0) as usize + 1,
+            ),
+            _ => (position, position + period.round() as usize),
+        };
+        let end = end.min(audio.len());
+        if start >= end {
+            break;
+        }
+
+        let epoch = (start..end)
+            .max_by(|&a, &b| audio[a].abs().partial_cmp(&audio[b].abs()).unwrap())
+            .unwrap();
+        epochs.push(Epoch {
+            position: epoch,
+            period: Some(period),
+        });
+        position = epoch + (period.round() as usize).max(1);
+    }
+
+    epochs
+}
+
+/// Fit LPC on a window centred on each epoch, see `Epoch`. Returns the coefficients and
+/// the gain of each epoch like `fit_lpc()` does for each hop, zeros where the fit failed.
+/// If two periods are too short for an order `p` fit, the unvoiced window is used.
+pub fn fit_lpc_at_epochs(
+    audio: &Array1<f32>,
+    epochs: &[Epoch],
+    p: usize,
+    hop_size: usize,
+    config: &AnalysisConfig,
+) -> (Array2<f32>, Array1<f32>) {
+    let audio = lfilter(&array![1.0, -config.pre_emphasis], audio);
+
+    let mut lpc_coefficients = Array2::zeros((epochs.len(), p + 1));
+    let mut gain = Array1::zeros(epochs.len());
+
+    for (i, epoch) in epochs.iter().enumerate() {
+        let window = match epoch.period.map(|period| (2.0 * period).round() as usize) {
+            Some(window_size) if window_size > p => config.window_type.window(window_size),
+            _ => config.window(hop_size),
+        };
+        // Zero-pad at the edges of the audio
+        let start = epoch.position as isize - window.len() as isize / 2;
+        let windowed_audio = Array1::from_iter(window.iter().enumerate().map(|(j, w)| {
+            let index = start + j as isize;
+            if index >= 0 && (index as usize) < audio.len() {
+                audio[index as usize] * w
+            } else {
+                0.0
+            }
+        }));
+
+        if let Some((cur_lpc_coefficients, cur_gain, _residual)) =
+            fit_frame(&windowed_audio, p, config.warping)
+        {
+            lpc_coefficients.row_mut(i).assign(&cur_lpc_coefficients);
+            gain[i] = cur_gain;
+        }
+    }
+
+    (lpc_coefficients, gain)
+}
+
+/// Resample (frequencies, magnitudes) of shape (n_epochs, n_waves) onto the centres of
+/// `n_hops` hops by linear interpolation between the neighbouring epochs. If a wave is
+/// missing (frequency 0) in one of them, the nearer epoch is used instead.
+pub fn resample_tracks(
+    frequencies: ArrayView2<f32>,
+    magnitudes: ArrayView2<f32>,
+    epochs: &[Epoch],
+    n_hops: usize,
+    hop_size: usize,
+) -> (Array2<f32>, Array2<f32>) {
+    let n_waves = frequencies.ncols();
+    let mut hop_frequencies = Array2::zeros((n_hops, n_waves));
+    let mut hop_magnitudes = Array2::zeros((n_hops, n_waves));
+    if epochs.is_empty() {
+        return (hop_frequencies, hop_magnitudes);
+    }
+
+    for hop in 0..n_hops {
+        let centre = (hop * hop_size + hop_size / 2) as f32;
+        // The first epoch after the centre, clamped to the ends
+        let next = epochs
+            .iter()
+            .position(|epoch| epoch.position as f32 > centre)
+            .unwrap_or(epochs.len() - 1)
+            .max(1)
+            .min(epochs.len() - 1);
+        let previous = next.saturating_sub(1);
+        let t = if previous == next {
+            0.0
+        } else {
+            let (a, b) = (
+                epochs[previous].position as f32,
+                epochs[next].position as f32,
+            );
+            ((centre - a) / (b - a)).clamp(0.0, 1.0)
+        };
+
+        for wave in 0..n_waves {
+            let (f0, f1) = (frequencies[[previous, wave]], frequencies[[next, wave]]);
+            let (m0, m1) = (magnitudes[[previous, wave]], magnitudes[[next, wave]]);
+            let (frequency, magnitude) = if f0 > 0.0 && f1 > 0.0 {
+                (f0 + t * (f1 - f0), m0 + t * (m1 - m0))
+            } else if t < 0.5 {
+                (f0, m0)
+            } else {
+                (f1, m1)
+            };
+            hop_frequencies[[hop, wave]] = frequency;
+            hop_magnitudes[[hop, wave]] = magnitude;
+        }
+    }
+
+    (hop_frequencies, hop_magnitudes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cepstrum::tests::vowel;
+    use crate::signal_processing::tests::assert_array2_eq;
+
+    #[test]
+    fn test_epochs_follow_glottal_pulses() {
+        // Pulses every 20 samples, the resonator peaks a few samples after each of them
+        let audio = vowel(20, &[0.5], 400);
+        let epochs = find_epochs(audio.view(), &[Some(20.0), Some(20.0)], 200);
+
+        assert_eq!(epochs.len(), 20);
+        for (i, epoch) in epochs.iter().enumerate() {
+            assert!(epoch.position >= i * 20 && epoch.position < i * 20 + 5);
+            assert_eq!(epoch.period, Some(20.0));
+        }
+    }
+
+    #[test]
+    fn test_unvoiced_epochs_at_hop_centres() {
+        let audio = vowel(20, &[0.5], 400);
+        let epochs = find_epochs(audio.view(), &[None, Some(20.0), None, None], 100);
+
+        let unvoiced: Vec<usize> = epochs
+            .iter()
+            .filter(|epoch| epoch.period.is_none())
+            .map(|epoch| epoch.position)
+            .collect();
+        // The voiced epochs of the second hop run a bit into the third one
+        assert_eq!(unvoiced[0], 50);
+        assert!(unvoiced[1] >= 250 && unvoiced[1] < 260);
+        assert_eq!(unvoiced[2], 350);
+        assert!(epochs.windows(2).all(|w| w[0].position < w[1].position));
+    }
+
+    #[test]
+    fn test_short_periods_use_the_unvoiced_window() {
+        // Two periods of 8 samples are too short for an order 20 fit
+        let audio = vowel(8, &[0.5, 1.5], 512);
+        let epochs = [
+            Epoch {
+                position: 256,
+                period: Some(8.0),
+            },
+            Epoch {
+                position: 256,
+                period: None,
+            },
+        ];
+        let (lpc_coefficients, gain) =
+            fit_lpc_at_epochs(&audio, &epochs, 20, 128, &AnalysisConfig::default());
+        assert!(gain[0] > 0.0);
+        assert_eq!(lpc_coefficients.row(0), lpc_coefficients.row(1));
+    }
+
+    #[test]
+    fn test_resample_tracks() {
+        let epochs: Vec<Epoch> = [10, 30, 50]
+            .iter()
+            .map(|&position| Epoch {
+                position,
+                period: Some(20.0),
+            })
+            .collect();
+        let frequencies = array![[1.0, 2.0], [2.0, 0.0], [3.0, 4.0]];
+        let magnitudes = array![[1.0, 1.0], [1.0, 0.0], [3.0, 1.0]];
+
+        // Hop centres at 5, 15, 25 and 35. The second wave is missing in the middle epoch.
+        let (hop_frequencies, hop_magnitudes) =
+            resample_tracks(frequencies.view(), magnitudes.view(), &epochs, 4, 10);
+        assert_array2_eq(
+            &hop_frequencies,
+            &array![[1.0, 2.0], [1.25, 2.0], [1.75, 0.0], [2.25, 0.0]],
+            1e-6,
+        );
+        assert_array2_eq(
+            &hop_magnitudes,
+            &array![[1.0, 1.0], [1.0, 1.0], [1.0, 0.0], [1.5, 0.0]],
+            1e-6,
+        );
+    }
+}
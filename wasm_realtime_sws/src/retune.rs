@@ -0,0 +1,215 @@
+/// Distance between two frequencies in cents. The unit of the frequencies doesn't matter.
+fn cents_between(a: f32, b: f32) -> f32 {
+    1200.0 * (b / a).log2()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct WaveState {
+    /// The quantized frequency we're gliding towards.
+    target: f32,
+    /// Where the current glide started.
+    glide_start: f32,
+    /// 0 at the start of the glide, 1 once we've reached the target.
+    glide_progress: f32,
+    /// The allowed note nearest to the target when it was chosen, if the allowed notes
+    /// were known. The target is only held while this note is still allowed.
+    note: Option<f32>,
+}
+
+/// Makes quantization stateful, like an autotune:
+/// - Hysteresis: a wave only switches to a new note once the unquantized frequency is
+///   `hysteresis_cents` past the boundary between the current note and the new one.
+///   Without this, a formant sitting between two notes flips between them every hop.
+///   If the current note is no longer allowed (e.g. the key changed), there is nothing
+///   to hold on to and the wave switches right away.
+/// - Retune speed: instead of jumping to the new note, we glide there linearly
+///   (in cents) over `retune_seconds`.
+///
+/// This works on top of any quantization because it only looks at the unquantized
+/// and quantized frequencies.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Retuner {
+    pub hysteresis_cents: f32,
+    pub retune_seconds: f32,
+    waves: Vec<Option<WaveState>>,
+}
+
+impl Retuner {
+    pub fn new(hysteresis_cents: f32, retune_seconds: f32) -> Retuner {
+        Retuner {
+            hysteresis_cents,
+            retune_seconds,
+            waves: Vec::new(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.waves.clear();
+    }
+
+    /// Process one frame. `unquantized` and `quantized` contain one frequency per wave.
+    /// `allowed_notes` are the notes the quantization chooses from, in the same unit.
+    /// If empty, the current note is assumed to always stay allowed.
+    /// `seconds_per_frame` is how much time passes between two frames.
+    pub fn process_frame(
+        &mut self,
+        unquantized: &[f32],
+        quantized: &[f32],
+        allowed_notes: &[f32],
+        seconds_per_frame: f32,
+    ) -> Vec<f32> {
+        assert_eq!(unquantized.len(), quantized.len());
+        if self.waves.len() != quantized.len() {
+            // The number of waves changed, the old state is meaningless
+            self.waves = vec![None; quantized.len()];
+        }
+
+        let progress_per_frame = if self.retune_seconds > 0.0 {
+            seconds_per_frame / self.retune_seconds
+        } else {
+            1.0
+        };
+
+        let mut result = Vec::with_capacity(quantized.len());
+        for ((state, &raw), &proposed) in self.waves.iter_mut().zip(unquantized).zip(quantized) {
+            if raw <= 0.0 || proposed <= 0.0 {
+                // No formant, nothing to hold on to
+                *state = None;
+                result.push(proposed);
+                continue;
+            }
+
+            let cur = match state {
+                None => {
+                    *state = Some(WaveState {
+                        target: proposed,
+                        glide_start: proposed,
+                        glide_progress: 1.0,
+                        note: nearest_note(proposed, allowed_notes),
+                    });
+                    result.push(proposed);
+                    continue;
+                }
+                Some(cur) => cur,
+            };
+
+            let current_output = glide_position(cur);
+
+            // Moving `x` cents past the midpoint between the two notes makes us `x` cents
+            // further from the old one and `x` cents closer to the new one.
+            let distance_to_current = cents_between(raw, cur.target).abs();
+            let distance_to_proposed = cents_between(raw, proposed).abs();
+            let is_tiny_change = cents_between(cur.target, proposed).abs() < 1.0;
+            let is_still_allowed = match cur.note {
+                Some(note) => allowed_notes.is_empty() || is_allowed(note, allowed_notes),
+                None => true,
+            };
+
+            if is_tiny_change {
+                // Either the same note or e.g. partial quantization that follows the input
+                // a little. No reason to hold on to the old value, but keep gliding.
+                cur.target = proposed;
+            } else if !is_still_allowed
+                || distance_to_current - distance_to_proposed > 2.0 * self.hysteresis_cents
+            {
+                cur.target = proposed;
+                cur.glide_start = current_output;
+                cur.glide_progress = 0.0;
+                cur.note = nearest_note(proposed, allowed_notes);
+            }
+
+            cur.glide_progress = (cur.glide_progress + progress_per_frame).min(1.0);
+            result.push(glide_position(cur));
+        }
+
+        result
+    }
+}
+
+/// The allowed note closest to `frequency`, None if there are no allowed notes.
+fn nearest_note(frequency: f32, allowed_notes: &[f32]) -> Option<f32> {
+    allowed_notes.iter().copied().min_by(|a, b| {
+        let distance = |note: f32| cents_between(frequency, note).abs();
+        distance(*a).partial_cmp(&distance(*b)).unwrap()
+    })
+}
+
+fn is_allowed(note: f32, allowed_notes: &[f32]) -> bool {
+    allowed_notes
+        .iter()
+        .any(|&allowed| cents_between(note, allowed).abs() < 1.0)
+}
+
+/// Linear interpolation in cents between the glide start and the target.
+fn glide_position(state: &WaveState) -> f32 {
+    state.glide_start * (state.target / state.glide_start).powf(state.glide_progress)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hysteresis() {
+        // The boundary between 100 and 200 is at 141.42
+        let mut retuner = Retuner::new(50.0, 0.0);
+        let quantize = |x: f32| if x < 141.42 { 100.0 } else { 200.0 };
+
+        let mut outputs = Vec::new();
+        for raw in [130.0, 145.0, 150.0, 146.0, 160.0, 138.0, 120.0] {
+            outputs.extend(retuner.process_frame(&[raw], &[quantize(raw)], &[], 0.01));
+        }
+        // 50 cents past 141.42 is ~145.6 and 50 cents before is ~137.4
+        assert_eq!(
+            outputs,
+            vec![100.0, 100.0, 200.0, 200.0, 200.0, 200.0, 100.0]
+        );
+    }
+
+    #[test]
+    fn test_retune_speed() {
+        let mut retuner = Retuner::new(0.0, 0.04);
+        retuner.process_frame(&[100.0], &[100.0], &[], 0.01);
+
+        let mut outputs = Vec::new();
+        for _ in 0..5 {
+            outputs.extend(retuner.process_frame(&[200.0], &[200.0], &[], 0.01));
+        }
+        // A quarter of an octave per frame
+        let expected = [0.25, 0.5, 0.75, 1.0, 1.0].map(|x| 100.0 * 2.0f32.powf(x));
+        for (output, expected) in outputs.iter().zip(expected.iter()) {
+            assert!((output - expected).abs() < 1e-3);
+        }
+
+        // Missing formants reset the state
+        assert_eq!(retuner.process_frame(&[0.0], &[0.0], &[], 0.01), vec![0.0]);
+        assert_eq!(
+            retuner.process_frame(&[100.0], &[100.0], &[], 0.01),
+            vec![100.0]
+        );
+    }
+
+    #[test]
+    fn test_note_no_longer_allowed() {
+        let mut retuner = Retuner::new(50.0, 0.0);
+        let quantize = |x: f32, allowed: &[f32]| nearest_note(x, allowed).unwrap();
+
+        // Hold 200 while the input is just past the boundary to 100
+        let allowed = [100.0, 200.0];
+        let mut outputs = Vec::new();
+        for raw in [150.0, 140.0] {
+            outputs.extend(retuner.process_frame(
+                &[raw],
+                &[quantize(raw, &allowed)],
+                &allowed,
+                0.01,
+            ));
+        }
+        assert_eq!(outputs, vec![200.0, 200.0]);
+
+        // 200 is no longer allowed, so switch even though we're within the hysteresis
+        let allowed = [100.0, 210.0];
+        let output = retuner.process_frame(&[140.0], &[quantize(140.0, &allowed)], &allowed, 0.01);
+        assert_eq!(output, vec![100.0]);
+    }
+}
@@ -1,4 +1,6 @@
 use ndarray::{Array1, ArrayView1};
+use rustfft::{algorithm::Radix4, num_complex::Complex, Fft, FftDirection};
+use wasm_bindgen::prelude::*;
 
 pub fn lfilter(coeffs: &Array1<f32>, signal: &Array1<f32>) -> Array1<f32> {
     let n = signal.len();
@@ -33,6 +35,49 @@ pub fn autocorrelate(signal: ArrayView1<f32>) -> Array1<f32> {
     result
 }
 
+/// First-order allpass filter (z^-1 - warping) / (1 - warping z^-1). Applying it instead of
+/// a unit delay warps the frequency axis, see `warp_frequency()`.
+fn allpass(signal: ArrayView1<f32>, warping: f32) -> Array1<f32> {
+    let mut previous_input = 0.0;
+    let mut previous_output = 0.0;
+    signal.mapv(|x| {
+        let y = -warping * x + previous_input + warping * previous_output;
+        previous_input = x;
+        previous_output = y;
+        y
+    })
+}
+
+/// Where the normalized frequency `frequency` ends up on the warped frequency axis. For positive
+/// `warping`, low frequencies are stretched out. Warping by `-warping` undoes this.
+pub fn warp_frequency(frequency: f32, warping: f32) -> f32 {
+    frequency + 2.0 * (warping * frequency.sin() / (1.0 - warping * frequency.cos())).atan()
+}
+
+/// Like `autocorrelate()` but on a warped frequency axis, for lags up to `max_lag`:
+/// lag k correlates the signal with itself passed through k allpass filters
+/// instead of delayed by k samples. Without warping, this is the same as `autocorrelate()`.
+pub fn warped_autocorrelate(signal: ArrayView1<f32>, max_lag: usize, warping: f32) -> Array1<f32> {
+    let mut result = Array1::zeros(max_lag + 1);
+    let mut delayed = signal.to_owned();
+    for lag in 0..=max_lag {
+        result[lag] = signal.dot(&delayed);
+        delayed = allpass(delayed.view(), warping);
+    }
+    result
+}
+
+/// Like `lfilter()` with an FIR filter, but with each unit delay replaced by an allpass filter.
+pub fn warped_lfilter(coeffs: &Array1<f32>, signal: &Array1<f32>, warping: f32) -> Array1<f32> {
+    let mut output = Array1::zeros(signal.len());
+    let mut delayed = signal.clone();
+    for coeff in coeffs {
+        output.scaled_add(*coeff, &delayed);
+        delayed = allpass(delayed.view(), warping);
+    }
+    output
+}
+
 pub fn hann_window(size: usize) -> Array1<f32> {
     Array1::from_iter((0..size).map(|n| {
         let x = 2.0 * std::f32::consts::PI * n as f32 / size as f32;
@@ -40,6 +85,95 @@ pub fn hann_window(size: usize) -> Array1<f32> {
     }))
 }
 
+#[wasm_bindgen]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WindowType {
+    Hann,
+    Hamming,
+    Blackman,
+    /// Standard deviation of 0.4 times half the window size.
+    Gaussian,
+    /// Rises slowly and falls quickly, so the analysis reacts to the newest samples sooner.
+    LowLatency,
+}
+
+impl WindowType {
+    /// Periodic (not symmetric) windows, like `hann_window()`.
+    pub fn window(self, size: usize) -> Array1<f32> {
+        let cosine_sum = |coefficients: &[f32]| {
+            Array1::from_iter((0..size).map(|n| {
+                let x = 2.0 * std::f32::consts::PI * n as f32 / size as f32;
+                coefficients
+                    .iter()
+                    .enumerate()
+                    .map(|(k, a)| a * (k as f32 * x).cos() * if k % 2 == 1 { -1.0 } else { 1.0 })
+                    .sum()
+            }))
+        };
+
+        match self {
+            WindowType::Hann => hann_window(size),
+            WindowType::Hamming => cosine_sum(&[0.54, 0.46]),
+            WindowType::Blackman => cosine_sum(&[0.42, 0.5, 0.08]),
+            WindowType::Gaussian => {
+                let sigma = 0.4 * size as f32 / 2.0;
+                Array1::from_iter((0..size).map(|n| {
+                    let x = (n as f32 - size as f32 / 2.0) / sigma;
+                    (-0.5 * x * x).exp()
+                }))
+            }
+            WindowType::LowLatency => {
+                // The rising half of a long Hann window followed by the falling half
+                // of a short one that takes up the last eighth of the window.
+                let fall_size = (size / 8).max(1).min(size);
+                let rise_size = size - fall_size;
+                let rise = hann_window(2 * rise_size);
+                let fall = hann_window(2 * fall_size);
+                Array1::from_iter(
+                    rise.iter()
+                        .take(rise_size)
+                        .chain(fall.iter().skip(fall_size))
+                        .copied(),
+                )
+            }
+        }
+    }
+}
+
+/// In-place FFT of a power-of-two length. We don't use `FftPlanner` because it would pull
+/// the algorithms for all the other lengths into the wasm binary.
+pub fn fft_in_place(buffer: &mut [Complex<f32>], direction: FftDirection) {
+    Radix4::new(buffer.len(), direction).process(buffer);
+}
+
+/// Magnitudes of the FFT of `signal`, zero-padded (or truncated) to `fft_size`,
+/// which must be a power of two.
+/// Only the non-negative frequencies are returned, so there are `fft_size / 2 + 1` bins,
+/// where bin k corresponds to k * sample_rate / fft_size Hz.
+pub fn magnitude_spectrum(signal: ArrayView1<f32>, fft_size: usize) -> Array1<f32> {
+    let mut buffer: Vec<Complex<f32>> = (0..fft_size)
+        .map(|i| Complex::new(signal.get(i).copied().unwrap_or(0.0), 0.0))
+        .collect();
+
+    fft_in_place(&mut buffer, FftDirection::Forward);
+
+    buffer[..fft_size / 2 + 1]
+        .iter()
+        .map(|x| x.norm())
+        .collect()
+}
+
+/// Magnitude of the discrete-time Fourier transform of `signal` at a single normalized
+/// frequency (radians/sample). Useful when the frequencies don't lie on an FFT grid.
+pub fn dtft_magnitude(signal: ArrayView1<f32>, frequency: f32) -> f32 {
+    let sum: Complex<f32> = signal
+        .iter()
+        .enumerate()
+        .map(|(n, x)| Complex::from_polar(*x, -frequency * n as f32))
+        .sum();
+    sum.norm()
+}
+
 /// A-weighting curve for loudness perception
 pub fn a_weighing_loudness(frequency_hz: f32) -> f32 {
     // See https://en.wikipedia.org/wiki/A-weighting#A
@@ -151,6 +285,29 @@ pub mod tests {
         assert_array1_eq(&autocorrelation, &expected, 1e-6);
     }
 
+    #[test]
+    fn test_warping() {
+        let signal = array![1.0, 2.0, 3.0, 4.0, 5.0];
+        // No warping is the same as no allpass filters
+        assert_array1_eq(
+            &warped_autocorrelate(signal.view(), 2, 0.0),
+            &array![55.0, 40.0, 26.0],
+            1e-6,
+        );
+        let coeffs = array![1.0, 2.0];
+        assert_array1_eq(
+            &warped_lfilter(&coeffs, &signal, 0.0),
+            &lfilter(&coeffs, &signal),
+            1e-6,
+        );
+
+        for frequency in [0.1, 1.0, 3.0] {
+            let warped = warp_frequency(frequency, 0.6);
+            assert!(warped > frequency);
+            assert!((warp_frequency(warped, -0.6) - frequency).abs() < 1e-5);
+        }
+    }
+
     #[test]
     fn test_lfilter() {
         let coeffs = Array1::from_vec(vec![1.0, 2.0]);
@@ -163,6 +320,7 @@ pub mod tests {
     }
 
     #[test]
+    #[allow(clippy::excessive_precision)]
     fn test_hann_window() {
         let window = hann_window(4);
         let expected = array![0.0, 0.5, 1.0, 0.5];
@@ -179,12 +337,47 @@ pub mod tests {
         assert_array1_eq(&window, &expected, 1e-6);
     }
 
+    #[test]
+    fn test_window_types() {
+        // Values from scipy.signal.get_window(name, 8)
+        let expected = [
+            (
+                WindowType::Hamming,
+                array![0.08, 0.21473, 0.54, 0.86527, 1.0, 0.86527, 0.54, 0.21473],
+            ),
+            (
+                WindowType::Blackman,
+                array![0.0, 0.06645, 0.34, 0.77355, 1.0, 0.77355, 0.34, 0.06645],
+            ),
+        ];
+        for (window_type, expected) in expected {
+            assert_array1_eq(&window_type.window(8), &expected, 1e-5);
+        }
+
+        let window = WindowType::LowLatency.window(16);
+        assert_eq!(window.len(), 16);
+        // Peaks right before the short falling part
+        assert_eq!(window[14], 1.0);
+        assert!(window[1] < window[15]);
+    }
+
+    #[test]
+    fn test_magnitude_spectrum() {
+        // A cosine that completes 2 cycles in 8 samples lands in bin 2
+        let signal =
+            Array1::from_iter((0..8).map(|i| (std::f32::consts::PI * i as f32 / 2.0).cos()));
+        let spectrum = magnitude_spectrum(signal.view(), 8);
+        let expected = array![0.0, 0.0, 4.0, 0.0, 0.0];
+
+        assert_array1_eq(&spectrum, &expected, 1e-5);
+    }
+
     #[test]
     fn test_equal_loudness_compensation() {
         for value in [20.0, 100.0, 1000.0, 2000.0, 4000.0, 8000.0, 20000.0].iter() {
             let compensation = equal_loudness_compensation(*value);
             assert!(
-                0.05 <= compensation && compensation <= 4.0,
+                (0.05..=4.0).contains(&compensation),
                 "Frequency: {}, Compensation: {}",
                 value,
                 compensation
@@ -0,0 +1,215 @@
+use ndarray::prelude::*;
+
+use crate::signal_processing::magnitude_spectrum;
+
+/// A spectral peak with parabolic interpolation, in normalized frequency.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Peak {
+    pub frequency: f32,
+    /// Amplitude of the sine wave that would produce this peak.
+    pub amplitude: f32,
+}
+
+/// Find the local maxima of the spectrum of a windowed frame. `window_sum` is the sum of the
+/// window, used to convert peak heights to sine amplitudes. Peaks more than `threshold_db`
+/// below the strongest one are ignored. Returned strongest first.
+pub fn find_peaks(frame: ArrayView1<f32>, window_sum: f32, threshold_db: f32) -> Vec<Peak> {
+    // Zero-pad for a finer grid, the interpolation takes care of the rest
+    let fft_size = 2 * frame.len().next_power_of_two();
+    let spectrum_db = magnitude_spectrum(frame, fft_size).mapv(|x| 20.0 * x.max(1e-10).log10());
+
+    let mut peaks: Vec<Peak> = interpolated_peaks(spectrum_db.view(), threshold_db)
+        .into_iter()
+        .map(|(bin, peak_db)| Peak {
+            frequency: bin * 2.0 * std::f32::consts::PI / fft_size as f32,
+            // A sine of amplitude A gives a peak of A / 2 * sum(window)
+            amplitude: 10f32.powf(peak_db / 20.0) * 2.0 / window_sum,
+        })
+        .collect();
+
+    peaks.sort_by(|a, b| b.amplitude.partial_cmp(&a.amplitude).unwrap());
+    peaks
+}
+
+/// Local maxima of a spectrum in dB as (fractional bin, height in dB), in order of
+/// frequency. Peaks more than `threshold_db` below the highest one are ignored.
+pub fn interpolated_peaks(spectrum_db: ArrayView1<f32>, threshold_db: f32) -> Vec<(f32, f32)> {
+    let max_db = spectrum_db.fold(f32::NEG_INFINITY, |a, b| a.max(*b));
+
+    (1..spectrum_db.len() - 1)
+        .filter(|&k| {
+            spectrum_db[k] > spectrum_db[k - 1]
+                && spectrum_db[k] >= spectrum_db[k + 1]
+                && spectrum_db[k] > max_db - threshold_db
+        })
+        .map(|k| {
+            // Fit a parabola through the log magnitudes around the peak
+            let (alpha, beta, gamma) = (spectrum_db[k - 1], spectrum_db[k], spectrum_db[k + 1]);
+            let offset = 0.5 * (alpha - gamma) / (alpha - 2.0 * beta + gamma);
+            (k as f32 + offset, beta - 0.25 * (alpha - gamma) * offset)
+        })
+        .collect()
+}
+
+/// McAulay-Quatieri style sinusoidal analysis: pick the spectral peaks of each frame and
+/// continue them into tracks. Each wave is one track, so waves don't jump between
+/// unrelated peaks. Works better than LPC for music and singing.
+///
+/// A track continues to the nearest free peak within `max_deviation_cents`, closest
+/// matches first. Tracks without a match die (frequency 0) and the strongest unmatched
+/// peaks are born into free waves, lowest frequency into the first free wave.
+///
+/// Unlike with LPC, the waves are therefore not always sorted by frequency: a track born
+/// later or crossing another one keeps its wave. Per-wave settings such as formant offsets
+/// or register ranges follow the tracks, not F1, F2, ...
+#[derive(Debug, Clone, PartialEq)]
+pub struct SinusoidalAnalyzer {
+    pub max_deviation_cents: f32,
+    pub threshold_db: f32,
+    /// Frequency of each track in the last frame, 0 for no track.
+    previous: Vec<f32>,
+}
+
+impl SinusoidalAnalyzer {
+    pub fn new(max_deviation_cents: f32, threshold_db: f32) -> SinusoidalAnalyzer {
+        SinusoidalAnalyzer {
+            max_deviation_cents,
+            threshold_db,
+            previous: Vec::new(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.previous.clear();
+    }
+
+    /// `frames` has shape (n_frames, window_size) and is already windowed with a window
+    /// that sums to `window_sum`. Returns (frequencies, magnitudes) of shape (n_frames, n_waves).
+    pub fn analyze(
+        &mut self,
+        frames: ArrayView2<f32>,
+        window_sum: f32,
+        n_waves: usize,
+    ) -> (Array2<f32>, Array2<f32>) {
+        if self.previous.len() != n_waves {
+            self.previous = vec![0.0; n_waves];
+        }
+
+        let mut frequencies = Array2::zeros((frames.nrows(), n_waves));
+        let mut magnitudes = Array2::zeros((frames.nrows(), n_waves));
+
+        for (i, frame) in frames.outer_iter().enumerate() {
+            let peaks = find_peaks(frame, window_sum, self.threshold_db);
+            let assignment = self.continue_tracks(&peaks);
+
+            for (wave, peak) in assignment.iter().enumerate() {
+                if let Some(peak) = peak {
+                    frequencies[[i, wave]] = peak.frequency;
+                    magnitudes[[i, wave]] = peak.amplitude;
+                }
+            }
+            self.previous = frequencies.row(i).to_vec();
+        }
+
+        (frequencies, magnitudes)
+    }
+
+    fn continue_tracks(&self, peaks: &[Peak]) -> Vec<Option<Peak>> {
+        let cents = |a: f32, b: f32| (1200.0 * (a / b).log2()).abs();
+
+        // All (distance, wave, peak) candidates, closest first
+        let mut candidates: Vec<(f32, usize, usize)> = Vec::new();
+        for (wave, &previous) in self.previous.iter().enumerate() {
+            if previous <= 0.0 {
+                continue;
+            }
+            for (peak_index, peak) in peaks.iter().enumerate() {
+                let distance = cents(previous, peak.frequency);
+                if distance <= self.max_deviation_cents {
+                    candidates.push((distance, wave, peak_index));
+                }
+            }
+        }
+        candidates.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+        let mut assignment = vec![None; self.previous.len()];
+        let mut used = vec![false; peaks.len()];
+        for (_, wave, peak_index) in candidates {
+            if assignment[wave].is_none() && !used[peak_index] {
+                assignment[wave] = Some(peaks[peak_index]);
+                used[peak_index] = true;
+            }
+        }
+
+        // Births: the peaks are sorted strongest first. Keep the waves in order of
+        // frequency where we can.
+        let n_free = assignment.iter().filter(|slot| slot.is_none()).count();
+        let mut births: Vec<Peak> = peaks
+            .iter()
+            .zip(&used)
+            .filter(|(_, used)| !**used)
+            .map(|(peak, _)| *peak)
+            .take(n_free)
+            .collect();
+        births.sort_by(|a, b| a.frequency.partial_cmp(&b.frequency).unwrap());
+        let mut births = births.into_iter();
+        for slot in assignment.iter_mut().filter(|slot| slot.is_none()) {
+            *slot = births.next();
+        }
+        assignment
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signal_processing::hann_window;
+
+    fn windowed_sines(sines: &[(f32, f32)], start: usize, window: &Array1<f32>) -> Array1<f32> {
+        Array1::from_iter((0..window.len()).map(|i| {
+            let t = (start + i) as f32;
+            sines.iter().map(|(f, a)| a * (f * t).sin()).sum::<f32>() * window[i]
+        }))
+    }
+
+    #[test]
+    fn test_find_peaks() {
+        let window = hann_window(512);
+        let frame = windowed_sines(&[(0.3, 0.5), (1.1, 0.2)], 0, &window);
+        let peaks = find_peaks(frame.view(), window.sum(), 30.0);
+
+        assert_eq!(peaks.len(), 2);
+        assert!((peaks[0].frequency - 0.3).abs() < 1e-3);
+        assert!((peaks[0].amplitude - 0.5).abs() < 0.01);
+        assert!((peaks[1].frequency - 1.1).abs() < 1e-3);
+        assert!((peaks[1].amplitude - 0.2).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_tracks_keep_their_waves() {
+        let window = hann_window(512);
+        let mut analyzer = SinusoidalAnalyzer::new(100.0, 30.0);
+
+        // The lower sine gets louder than the upper one but both keep their wave.
+        // Then the upper one disappears and a lower one is born into its wave.
+        let frames = [
+            windowed_sines(&[(0.5, 0.2), (0.8, 0.5)], 0, &window),
+            windowed_sines(&[(0.51, 0.6), (0.81, 0.3)], 256, &window),
+            windowed_sines(&[(0.52, 0.6)], 512, &window),
+            windowed_sines(&[(0.2, 0.3), (0.53, 0.6)], 768, &window),
+        ];
+        let views: Vec<_> = frames.iter().map(|f| f.view()).collect();
+        let frames = ndarray::stack(Axis(0), &views).unwrap();
+
+        let (frequencies, _magnitudes) = analyzer.analyze(frames.view(), window.sum(), 2);
+        // Tracks born together are sorted by frequency, not by strength
+        assert!((frequencies[[0, 0]] - 0.5).abs() < 1e-2);
+        assert!((frequencies[[0, 1]] - 0.8).abs() < 1e-2);
+        assert!((frequencies[[1, 0]] - 0.51).abs() < 1e-2);
+        assert!((frequencies[[1, 1]] - 0.81).abs() < 1e-2);
+        assert!((frequencies[[2, 0]] - 0.52).abs() < 1e-2);
+        assert_eq!(frequencies[[2, 1]], 0.0);
+        assert!((frequencies[[3, 0]] - 0.53).abs() < 1e-2);
+        assert!((frequencies[[3, 1]] - 0.2).abs() < 1e-2);
+    }
+}
@@ -3,13 +3,21 @@ use ndarray::{s, Array1, ArrayView2, Axis};
 /// Synthesizes a signal from sine wave frequencies and magnitudes.
 ///
 /// We don't need to know the sample rate because the frequencies are in radians/sample.
+///
+/// The hop size can be fractional and differ from the one used for analysis,
+/// which is how we do time-stretching.
+/// `start_offset` is the time (in samples, between 0 and 1) of the first output sample,
+/// relative to the first frame. Together with the returned offset, this lets us synthesize
+/// in chunks without the timing drifting: pass the returned offset to the next call.
+/// Returns the output, the last phases and the next offset.
 pub fn synthesize(
     normalized_frequencies: ArrayView2<f32>,
     magnitudes: ArrayView2<f32>,
-    hop_size: usize,
+    hop_size: f32,
+    start_offset: f32,
     wave_fn: impl Fn(f32) -> f32,
     first_phases: Option<Array1<f32>>,
-) -> (Array1<f32>, Array1<f32>) {
+) -> (Array1<f32>, Array1<f32>, f32) {
     assert_eq!(normalized_frequencies.shape(), magnitudes.shape());
     if normalized_frequencies.len_of(Axis(0)) < 2 {
         panic!(
@@ -22,7 +30,8 @@ pub fn synthesize(
         first_phases.unwrap_or_else(|| Array1::zeros(normalized_frequencies.dim().1));
 
     let (n_frames, n_waves) = normalized_frequencies.dim();
-    let output_samples = (n_frames - 1) * hop_size;
+    let duration = (n_frames - 1) as f32 * hop_size;
+    let output_samples = n_output_samples(duration, start_offset);
     let mut output = Array1::zeros(output_samples);
     let mut last_phases = Array1::zeros(n_waves);
 
@@ -34,6 +43,7 @@ pub fn synthesize(
             &freq_slice.to_owned(),
             &mag_slice.to_owned(),
             hop_size,
+            start_offset,
             &wave_fn,
             first_phases[i],
         );
@@ -44,14 +54,22 @@ pub fn synthesize(
     // Apply compression to avoid clipping
     output.mapv_inplace(atan_compression);
 
-    (output, last_phases)
+    let next_offset = start_offset + output_samples as f32 - duration;
+    (output, last_phases, next_offset)
+}
+
+/// How many samples fit into [start_offset, duration).
+fn n_output_samples(duration: f32, start_offset: f32) -> usize {
+    ((duration - start_offset).ceil().max(0.0)) as usize
 }
 
 /// Synthesize one wave from normalized frequencies and magnitudes.
+/// See `synthesize()` for the meaning of `start_offset`.
 pub fn synthesize_one(
     normalized_frequencies: &Array1<f32>,
     magnitudes: &Array1<f32>,
-    hop_size: usize,
+    hop_size: f32,
+    start_offset: f32,
     wave_fn: impl Fn(f32) -> f32,
     first_phase: f32,
 ) -> (Array1<f32>, f32) {
@@ -61,10 +79,21 @@ pub fn synthesize_one(
     let frequencies_upsampled = upsample(
         normalized_frequencies,
         hop_size,
+        start_offset,
         false,
         UpsamplingMethod::Nearest,
     );
-    let magnitudes_upsampled = upsample(magnitudes, hop_size, false, UpsamplingMethod::Linear);
+    let magnitudes_upsampled = upsample(
+        magnitudes,
+        hop_size,
+        start_offset,
+        false,
+        UpsamplingMethod::Linear,
+    );
+
+    if frequencies_upsampled.is_empty() {
+        return (Array1::zeros(0), first_phase);
+    }
 
     // Calculate cumulative sum for phase
     let mut phase = Array1::zeros(frequencies_upsampled.len());
@@ -76,7 +105,7 @@ pub fn synthesize_one(
 
     (
         // Apply wave function and scale by magnitudes
-        phase.mapv(|x| wave_fn(x)) * &magnitudes_upsampled,
+        phase.mapv(wave_fn) * &magnitudes_upsampled,
         phase[phase.len() - 1] % (2.0 * std::f32::consts::PI),
     )
 }
@@ -86,28 +115,31 @@ pub enum UpsamplingMethod {
     Nearest,
 }
 
-/// Upsamples a signal, stretching it by an integer factor.
+/// Upsamples a signal, stretching it by a factor that may be fractional.
 /// Linearly interpolates between the original samples.
-/// Returns an array of size (x.len() - 1) * factor,
-/// or one more if include_last is true.
-/// For example, if you have [a, b, c] and factor = 3, you end
+/// The output samples are at times `start_offset + k` (in output samples)
+/// for all k such that the time is smaller than (x.len() - 1) * factor,
+/// plus one more sample at the end if include_last is true.
+/// For example, if you have [a, b, c], factor = 3 and start_offset = 0, you end
 /// up with axxbxxc, where x represent interpolated values.
 /// If include_last is false, you get axxbxx.
 pub fn upsample(
     x: &Array1<f32>,
-    factor: usize,
+    factor: f32,
+    start_offset: f32,
     include_last: bool,
     method: UpsamplingMethod,
 ) -> Array1<f32> {
     // Replace NaN values with 0.0
     let x = x.mapv(|v| if v.is_nan() { 0.0 } else { v });
 
-    let output_size = (x.len() - 1) * factor + (if include_last { 1 } else { 0 });
+    let duration = (x.len() - 1) as f32 * factor;
+    let output_size = n_output_samples(duration, start_offset) + (if include_last { 1 } else { 0 });
     let mut output = Array1::zeros(output_size);
 
     for i in 0..output_size {
-        let global_fraction = i as f32 / factor as f32;
-        let i_low = global_fraction.floor() as usize;
+        let global_fraction = (start_offset + i as f32) / factor;
+        let i_low = (global_fraction.floor() as usize).min(x.len() - 1);
         let i_high = (i_low + 1).min(x.len() - 1);
         let local_fraction = global_fraction - i_low as f32;
 
@@ -130,22 +162,68 @@ fn atan_compression(x: f32) -> f32 {
 #[cfg(test)]
 mod tests {
     use crate::signal_processing::tests::assert_array1_eq;
+    use ndarray::Array2;
 
     use super::*;
 
     #[test]
     fn test_upsample() {
         let input = Array1::from_vec(vec![0.0, 1.0, 2.0]);
-        let upsampled = upsample(&input, 2, true, UpsamplingMethod::Linear);
+        let upsampled = upsample(&input, 2.0, 0.0, true, UpsamplingMethod::Linear);
         let expected = Array1::from_vec(vec![0.0, 0.5, 1.0, 1.5, 2.0]);
         assert_array1_eq(&upsampled, &expected, 1e-6);
 
-        let upsampled = upsample(&input, 2, false, UpsamplingMethod::Linear);
+        let upsampled = upsample(&input, 2.0, 0.0, false, UpsamplingMethod::Linear);
         let expected = Array1::from_vec(vec![0.0, 0.5, 1.0, 1.5]);
         assert_array1_eq(&upsampled, &expected, 1e-6);
 
-        let upsampled = upsample(&input, 2, true, UpsamplingMethod::Nearest);
+        let upsampled = upsample(&input, 2.0, 0.0, true, UpsamplingMethod::Nearest);
         let expected = Array1::from_vec(vec![0.0, 0.0, 1.0, 1.0, 2.0]);
         assert_array1_eq(&upsampled, &expected, 1e-6);
     }
+
+    #[test]
+    fn test_upsample_fractional() {
+        let input = Array1::from_vec(vec![0.0, 1.0, 2.0]);
+        let upsampled = upsample(&input, 1.5, 0.5, false, UpsamplingMethod::Linear);
+        let expected = Array1::from_vec(vec![1. / 3., 1.0, 5. / 3.]);
+        assert_array1_eq(&upsampled, &expected, 1e-6);
+    }
+
+    #[test]
+    fn test_synthesize_in_chunks() {
+        // Synthesizing in chunks should give the same result as doing it all at once
+        let frequencies = Array2::from_shape_vec((4, 1), vec![0.1, 0.2, 0.3, 0.2]).unwrap();
+        let magnitudes = Array2::from_shape_vec((4, 1), vec![1.0, 0.5, 0.8, 1.0]).unwrap();
+        let hop_size = 2.5;
+
+        let (full, _, _) = synthesize(
+            frequencies.view(),
+            magnitudes.view(),
+            hop_size,
+            0.0,
+            f32::sin,
+            None,
+        );
+
+        let mut chunks = Vec::new();
+        let mut phases = None;
+        let mut offset = 0.0;
+        for i in 0..3 {
+            let (chunk, last_phases, next_offset) = synthesize(
+                frequencies.slice(s![i..i + 2, ..]),
+                magnitudes.slice(s![i..i + 2, ..]),
+                hop_size,
+                offset,
+                f32::sin,
+                phases,
+            );
+            chunks.extend(chunk.to_vec());
+            phases = Some(last_phases);
+            offset = next_offset;
+        }
+
+        assert_eq!(full.len(), 8);
+        assert_array1_eq(&Array1::from_vec(chunks), &full, 1e-4);
+    }
 }
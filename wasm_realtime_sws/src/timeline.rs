@@ -0,0 +1,239 @@
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::music::{Key, NoteName, ScaleMode};
+
+/// Chord qualities that can be used in a timeline instead of a scale.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+pub enum ChordQuality {
+    #[serde(rename = "maj")]
+    Major,
+    #[serde(rename = "min")]
+    Minor,
+    #[serde(rename = "dim")]
+    Diminished,
+    #[serde(rename = "aug")]
+    Augmented,
+    #[serde(rename = "sus2")]
+    Suspended2,
+    #[serde(rename = "sus4")]
+    Suspended4,
+    #[serde(rename = "7")]
+    Dominant7,
+    #[serde(rename = "maj7")]
+    Major7,
+    #[serde(rename = "min7")]
+    Minor7,
+}
+
+impl ChordQuality {
+    pub fn intervals(&self) -> &'static [i32] {
+        match self {
+            ChordQuality::Major => &[0, 4, 7],
+            ChordQuality::Minor => &[0, 3, 7],
+            ChordQuality::Diminished => &[0, 3, 6],
+            ChordQuality::Augmented => &[0, 4, 8],
+            ChordQuality::Suspended2 => &[0, 2, 7],
+            ChordQuality::Suspended4 => &[0, 5, 7],
+            ChordQuality::Dominant7 => &[0, 4, 7, 10],
+            ChordQuality::Major7 => &[0, 4, 7, 11],
+            ChordQuality::Minor7 => &[0, 3, 7, 10],
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum TimelineError {
+    #[error("Invalid timeline JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Change {0}: invalid note name {1:?}")]
+    InvalidNoteName(usize, String),
+    #[error("Change {0}: exactly one of `seconds` and `beat` must be given")]
+    InvalidTime(usize),
+    #[error("Change {0}: `beat` requires `bpm` to be set")]
+    MissingTempo(usize),
+    #[error("Change {0}: exactly one of `mode`, `chord` and `intervals` must be given")]
+    InvalidHarmony(usize),
+    #[error("Change {0}: `intervals` must not be empty")]
+    EmptyIntervals(usize),
+    #[error("`bpm` must be positive, got {0}")]
+    InvalidTempo(f32),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TimelineJson {
+    bpm: Option<f32>,
+    changes: Vec<ChangeJson>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ChangeJson {
+    seconds: Option<f32>,
+    beat: Option<f32>,
+    root: String,
+    mode: Option<ScaleMode>,
+    chord: Option<ChordQuality>,
+    intervals: Option<Vec<i32>>,
+}
+
+/// A sequence of timestamped scale or chord changes that the quantization follows,
+/// e.g. to make speech follow a song's chord progression. Each change is resolved
+/// to a `Key` (chords become keys containing just the chord tones), so it works
+/// with all quantization types.
+///
+/// The JSON looks like this (times are in seconds of output, or in beats if `bpm` is given):
+/// ```json
+/// {
+///   "bpm": 120,
+///   "changes": [
+///     {"beat": 0, "root": "C", "mode": "Major"},
+///     {"beat": 4, "root": "A", "chord": "min7"},
+///     {"seconds": 4.0, "root": "F#", "intervals": [0, 4, 7]}
+///   ]
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timeline {
+    /// (start time in seconds, key) pairs sorted by time.
+    changes: Vec<(f32, Key)>,
+}
+
+impl Timeline {
+    pub fn from_json(json: &str) -> Result<Timeline, TimelineError> {
+        let parsed: TimelineJson = serde_json::from_str(json)?;
+        if let Some(bpm) = parsed.bpm {
+            if !(bpm.is_finite() && bpm > 0.0) {
+                return Err(TimelineError::InvalidTempo(bpm));
+            }
+        }
+
+        let mut changes = Vec::with_capacity(parsed.changes.len());
+        for (i, change) in parsed.changes.into_iter().enumerate() {
+            let seconds = match (change.seconds, change.beat) {
+                (Some(seconds), None) => seconds,
+                (None, Some(beat)) => {
+                    let bpm = parsed.bpm.ok_or(TimelineError::MissingTempo(i))?;
+                    beat * 60.0 / bpm
+                }
+                _ => return Err(TimelineError::InvalidTime(i)),
+            };
+
+            let root = parse_note_name(&change.root)
+                .ok_or_else(|| TimelineError::InvalidNoteName(i, change.root.clone()))?;
+            let key = match (change.mode, change.chord, change.intervals) {
+                (Some(mode), None, None) => Key::new(root, mode),
+                (None, Some(chord), None) => Key::custom(root, chord.intervals()),
+                (None, None, Some(intervals)) if intervals.is_empty() => {
+                    return Err(TimelineError::EmptyIntervals(i))
+                }
+                (None, None, Some(intervals)) => Key::custom(root, &intervals),
+                _ => return Err(TimelineError::InvalidHarmony(i)),
+            };
+            changes.push((seconds, key));
+        }
+        // Stable, so changes at the same time are applied in the order they were given
+        changes.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+        Ok(Timeline { changes })
+    }
+
+    /// The key in effect at the given time, or `None` before the first change.
+    pub fn key_at(&self, seconds: f32) -> Option<&Key> {
+        self.changes
+            .iter()
+            .take_while(|(start, _)| *start <= seconds)
+            .last()
+            .map(|(_, key)| key)
+    }
+}
+
+/// Parse names like "C", "F#" or "Bb".
+fn parse_note_name(name: &str) -> Option<NoteName> {
+    let mut chars = name.trim().chars();
+    let base = match chars.next()?.to_ascii_uppercase() {
+        'C' => 0,
+        'D' => 2,
+        'E' => 4,
+        'F' => 5,
+        'G' => 7,
+        'A' => 9,
+        'B' => 11,
+        _ => return None,
+    };
+    let mut pitch_class = base;
+    for accidental in chars {
+        match accidental {
+            '#' => pitch_class += 1,
+            'b' => pitch_class -= 1,
+            _ => return None,
+        }
+    }
+    Some(NoteName::from_pitch_class(pitch_class))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeline_from_json() {
+        let timeline = Timeline::from_json(
+            r#"{
+                "bpm": 120,
+                "changes": [
+                    {"beat": 4, "root": "A", "chord": "min"},
+                    {"beat": 0, "root": "C", "mode": "Major"},
+                    {"seconds": 4.0, "root": "Bb", "intervals": [0, 4, 7, 10]}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(timeline.key_at(-1.0), None);
+        assert_eq!(
+            timeline.key_at(1.9),
+            Some(&Key::new(NoteName::C, ScaleMode::Major))
+        );
+        // Beat 4 at 120 BPM is at 2 seconds
+        assert_eq!(
+            timeline.key_at(2.0).unwrap().scale(),
+            vec![NoteName::A, NoteName::C, NoteName::E]
+        );
+        assert_eq!(timeline.key_at(10.0).unwrap().root, NoteName::ASharp);
+    }
+
+    #[test]
+    fn test_timeline_errors() {
+        assert!(matches!(
+            Timeline::from_json(r#"{"changes": [{"beat": 0, "root": "C", "mode": "Major"}]}"#),
+            Err(TimelineError::MissingTempo(0))
+        ));
+        assert!(matches!(
+            Timeline::from_json(r#"{"changes": [{"seconds": 0, "root": "H", "chord": "maj"}]}"#),
+            Err(TimelineError::InvalidNoteName(0, _))
+        ));
+        assert!(matches!(
+            Timeline::from_json(
+                r#"{"changes": [{"seconds": 0, "root": "C", "mode": "Major", "chord": "maj"}]}"#
+            ),
+            Err(TimelineError::InvalidHarmony(0))
+        ));
+        assert!(matches!(
+            Timeline::from_json(r#"{"changes": [{"seconds": 0, "root": "C", "intervals": []}]}"#),
+            Err(TimelineError::EmptyIntervals(0))
+        ));
+        for bpm in ["0", "-120", "1e40"] {
+            let json = format!(r#"{{"bpm": {}, "changes": []}}"#, bpm);
+            assert!(matches!(
+                Timeline::from_json(&json),
+                Err(TimelineError::InvalidTempo(_))
+            ));
+        }
+        assert!(matches!(
+            Timeline::from_json("[]"),
+            Err(TimelineError::Json(_))
+        ));
+    }
+}
@@ -0,0 +1,190 @@
+use ndarray::ArrayViewMut2;
+
+/// Keeps waves from sweeping through the spectrum when their pole disappears or reappears.
+///
+/// Synthesis interpolates frequencies and magnitudes linearly between hops, so a wave whose
+/// pole is missing (frequency 0) would glide down to DC and a wave that comes back would
+/// glide up from wherever it was. Also, LPC and cepstral analysis sort each hop by frequency,
+/// so when a middle pole disappears, all higher waves would drop down one slot.
+///
+/// So each wave becomes a track: the poles of a frame continue the track of the previous
+/// frame with the nearest frequency, at most `max_deviation_cents` away, closest matches first.
+/// - Death: a track without a pole keeps its last frequency and its magnitude goes to 0,
+///   so it fades out over one hop.
+/// - Birth: an unmatched pole starts a track in the first free wave, lowest frequency first,
+///   with magnitude 0, and fades in over the next hop. This delays the onset by one hop.
+///   If all waves are taken (e.g. by tracks that are still fading out), it has to wait.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackContinuity {
+    pub max_deviation_cents: f32,
+    /// For each wave, the output frequency of the last frame and whether it had a pole.
+    previous: Vec<(f32, bool)>,
+}
+
+impl TrackContinuity {
+    pub fn new(max_deviation_cents: f32) -> TrackContinuity {
+        TrackContinuity {
+            max_deviation_cents,
+            previous: Vec::new(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.previous.clear();
+    }
+
+    /// Modifies frames of shape (n_frames, n_waves) in place.
+    pub fn process(
+        &mut self,
+        mut frequencies: ArrayViewMut2<f32>,
+        mut magnitudes: ArrayViewMut2<f32>,
+    ) {
+        assert_eq!(frequencies.shape(), magnitudes.shape());
+        let n_waves = frequencies.ncols();
+        if self.previous.len() != n_waves {
+            self.previous = vec![(0.0, false); n_waves];
+        }
+
+        for (mut frequencies, mut magnitudes) in frequencies
+            .outer_iter_mut()
+            .zip(magnitudes.outer_iter_mut())
+        {
+            let mut poles: Vec<(f32, f32)> = frequencies
+                .iter()
+                .zip(magnitudes.iter())
+                .filter(|(frequency, _)| **frequency > 0.0)
+                .map(|(frequency, magnitude)| (*frequency, *magnitude))
+                .collect();
+            poles.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+            let assignment = self.continue_tracks(&poles);
+            for (wave, (frequency, magnitude)) in assignment.into_iter().enumerate() {
+                frequencies[wave] = frequency;
+                magnitudes[wave] = magnitude;
+            }
+        }
+    }
+
+    /// The (frequency, magnitude) of each wave for one frame given its poles sorted by
+    /// frequency. Updates `previous`.
+    fn continue_tracks(&mut self, poles: &[(f32, f32)]) -> Vec<(f32, f32)> {
+        let cents = |a: f32, b: f32| (1200.0 * (a / b).log2()).abs();
+
+        // All (distance, wave, pole) candidates, closest first
+        let mut candidates: Vec<(f32, usize, usize)> = Vec::new();
+        for (wave, &(previous, was_alive)) in self.previous.iter().enumerate() {
+            if !was_alive {
+                continue;
+            }
+            for (pole_index, &(frequency, _)) in poles.iter().enumerate() {
+                let distance = cents(previous, frequency);
+                if distance <= self.max_deviation_cents {
+                    candidates.push((distance, wave, pole_index));
+                }
+            }
+        }
+        candidates.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+        let mut assignment: Vec<Option<usize>> = vec![None; self.previous.len()];
+        let mut used = vec![false; poles.len()];
+        for (_, wave, pole_index) in candidates {
+            if assignment[wave].is_none() && !used[pole_index] {
+                assignment[wave] = Some(pole_index);
+                used[pole_index] = true;
+            }
+        }
+
+        let mut births = poles
+            .iter()
+            .zip(&used)
+            .filter(|(_, used)| !**used)
+            .map(|(pole, _)| *pole);
+
+        let mut result = Vec::with_capacity(self.previous.len());
+        for (previous, pole_index) in self.previous.iter_mut().zip(assignment) {
+            let (previous_frequency, was_alive) = *previous;
+            let (frequency, magnitude, is_alive) = match pole_index {
+                Some(pole_index) => (poles[pole_index].0, poles[pole_index].1, true),
+                // Hold the last frequency (if any) while fading out
+                None if was_alive => (previous_frequency, 0.0, false),
+                // Jump to the new frequency while silent, fade in from there
+                None => match births.next() {
+                    Some((frequency, _)) => (frequency, 0.0, true),
+                    None => (previous_frequency, 0.0, false),
+                },
+            };
+            *previous = (frequency, is_alive);
+            result.push((frequency, magnitude));
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::array;
+
+    #[test]
+    fn test_track_birth_and_death() {
+        let mut continuity = TrackContinuity::new(300.0);
+        let mut frequencies = array![[0.1, 0.0], [0.1, 0.5], [0.0, 0.5]];
+        let mut magnitudes = array![[1.0, 0.0], [1.0, 2.0], [0.0, 2.0]];
+        continuity.process(frequencies.view_mut(), magnitudes.view_mut());
+
+        // The first wave is born in the first frame and dies in the last one
+        assert_eq!(frequencies, array![[0.1, 0.0], [0.1, 0.5], [0.1, 0.5]]);
+        assert_eq!(magnitudes, array![[0.0, 0.0], [1.0, 0.0], [0.0, 2.0]]);
+
+        // The state carries over to the next chunk: the second wave is still alive
+        // and the first one comes back at a new frequency
+        let mut frequencies = array![[0.3, 0.55], [0.35, 0.0]];
+        let mut magnitudes = array![[1.0, 2.0], [1.0, 0.0]];
+        continuity.process(frequencies.view_mut(), magnitudes.view_mut());
+        assert_eq!(frequencies, array![[0.3, 0.55], [0.35, 0.55]]);
+        assert_eq!(magnitudes, array![[0.0, 2.0], [1.0, 0.0]]);
+    }
+
+    #[test]
+    fn test_middle_pole_disappears() {
+        let mut continuity = TrackContinuity::new(300.0);
+        // The analysis sorts by frequency, so the highest pole moves down a slot when
+        // the middle one disappears, and then a new pole appears far away from the others
+        let mut frequencies = array![
+            [0.1, 0.2, 0.4],
+            [0.1, 0.2, 0.4],
+            [0.1, 0.41, 0.0],
+            [0.1, 0.41, 0.0],
+            [0.1, 0.41, 0.8]
+        ];
+        let mut magnitudes = array![
+            [1.0, 1.0, 1.0],
+            [1.0, 1.0, 1.0],
+            [1.0, 1.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [1.0, 1.0, 1.0]
+        ];
+        continuity.process(frequencies.view_mut(), magnitudes.view_mut());
+
+        assert_eq!(
+            frequencies,
+            array![
+                [0.1, 0.2, 0.4],
+                [0.1, 0.2, 0.4],
+                [0.1, 0.2, 0.41],
+                [0.1, 0.2, 0.41],
+                [0.1, 0.8, 0.41]
+            ]
+        );
+        assert_eq!(
+            magnitudes,
+            array![
+                [0.0, 0.0, 0.0],
+                [1.0, 1.0, 1.0],
+                [1.0, 0.0, 1.0],
+                [1.0, 0.0, 1.0],
+                [1.0, 0.0, 1.0]
+            ]
+        );
+    }
+}
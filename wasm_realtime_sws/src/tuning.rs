@@ -0,0 +1,437 @@
+use thiserror::Error;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::music::NoteName;
+
+/// MIDI note number of A4.
+pub const A4_MIDI_NOTE: i32 = 69;
+/// MIDI note number of C4 ("middle C").
+pub const C4_MIDI_NOTE: i32 = 60;
+
+#[wasm_bindgen]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Temperament {
+    /// 12-tone equal temperament, the default.
+    Equal,
+    /// 5-limit just intonation relative to the tonic.
+    JustIntonation,
+    Pythagorean,
+    QuarterCommaMeantone,
+}
+
+impl Temperament {
+    /// Cents of the 12 chromatic degrees above the tonic.
+    fn degree_cents(self) -> Vec<f32> {
+        match self {
+            Temperament::Equal => (0..12).map(|i| i as f32 * 100.0).collect(),
+            Temperament::JustIntonation => [
+                (1, 1),
+                (16, 15),
+                (9, 8),
+                (6, 5),
+                (5, 4),
+                (4, 3),
+                (45, 32),
+                (3, 2),
+                (8, 5),
+                (5, 3),
+                (9, 5),
+                (15, 8),
+            ]
+            .iter()
+            .map(|(a, b)| ratio_to_cents(*a as f32 / *b as f32))
+            .collect(),
+            // A chain of pure fifths from Db to F#
+            Temperament::Pythagorean => chain_of_fifths_cents(
+                ratio_to_cents(1.5),
+                &[0, -5, 2, -3, 4, -1, 6, 1, -4, 3, -2, 5],
+            ),
+            // Fifths narrowed by a quarter of the syntonic comma, from Eb to G#,
+            // which gives pure major thirds.
+            Temperament::QuarterCommaMeantone => chain_of_fifths_cents(
+                ratio_to_cents(5.0f32.powf(0.25)),
+                &[0, 7, 2, -3, 4, -1, 6, 1, 8, 3, -2, 5],
+            ),
+        }
+    }
+}
+
+/// `positions[i]` is how many fifths above the tonic the i-th semitone is.
+fn chain_of_fifths_cents(fifth_cents: f32, positions: &[i32]) -> Vec<f32> {
+    positions
+        .iter()
+        .map(|p| (*p as f32 * fifth_cents).rem_euclid(1200.0))
+        .collect()
+}
+
+pub fn ratio_to_cents(ratio: f32) -> f32 {
+    1200.0 * ratio.log2()
+}
+
+/// Maps MIDI notes onto the degrees of a scale that doesn't need to have 12 notes,
+/// like the `.kbm` files of Scala: http://www.huygens-fokker.org/scala/help.htm#mappings
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyboardMapping {
+    /// Scale degree for each key in one repetition of the mapping, `None` for unmapped keys.
+    pub degrees: Vec<Option<i32>>,
+    /// Scale degree that the mapping is shifted by when it repeats.
+    pub octave_degree: i32,
+    /// Lowest and highest mapped MIDI notes.
+    pub first_note: i32,
+    pub last_note: i32,
+}
+
+/// Tells us the frequency of each MIDI note.
+///
+/// The model is the same as in Scala: a scale given as cents above degree 0 that repeats
+/// after `period_cents`, and a mapping from MIDI notes to degrees, where `middle_note`
+/// is degree 0 and `reference_note` sounds at `reference_frequency`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tuning {
+    /// Cents of each degree above degree 0. The first one is always 0.
+    pub degree_cents: Vec<f32>,
+    /// How many cents until the scale repeats, usually 1200 (an octave).
+    pub period_cents: f32,
+    pub middle_note: i32,
+    pub reference_note: i32,
+    pub reference_frequency: f32,
+    /// `None` means consecutive MIDI notes are consecutive degrees.
+    pub mapping: Option<KeyboardMapping>,
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Tuning::new(Temperament::Equal, NoteName::C, 440.0)
+    }
+}
+
+impl Tuning {
+    /// A 12-note tuning where A4 sounds at `a4_frequency`. For the non-equal temperaments,
+    /// `tonic` is the note the pure intervals are relative to.
+    pub fn new(temperament: Temperament, tonic: NoteName, a4_frequency: f32) -> Tuning {
+        Tuning {
+            degree_cents: temperament.degree_cents(),
+            period_cents: 1200.0,
+            middle_note: C4_MIDI_NOTE + tonic.pitch_class() as i32,
+            reference_note: A4_MIDI_NOTE,
+            reference_frequency: a4_frequency,
+            mapping: None,
+        }
+    }
+
+    /// Equal division of the octave into `divisions` steps, with consecutive MIDI notes
+    /// being consecutive steps. The tonic in octave 4 keeps its 12-TET frequency.
+    pub fn equal_division(divisions: usize, tonic: NoteName, a4_frequency: f32) -> Tuning {
+        if divisions == 0 {
+            panic!("the octave must be divided into at least one step");
+        }
+        let middle_note = C4_MIDI_NOTE + tonic.pitch_class() as i32;
+        Tuning {
+            degree_cents: (0..divisions)
+                .map(|i| i as f32 * 1200.0 / divisions as f32)
+                .collect(),
+            period_cents: 1200.0,
+            middle_note,
+            reference_note: middle_note,
+            reference_frequency: a4_frequency
+                * 2.0f32.powf((middle_note - A4_MIDI_NOTE) as f32 / 12.0),
+            mapping: None,
+        }
+    }
+
+    /// True if MIDI notes map to the 12 usual pitch classes, so it makes sense to talk
+    /// about note names and keys.
+    pub fn is_twelve_tone(&self) -> bool {
+        self.degree_cents.len() == 12 && self.mapping.is_none()
+    }
+
+    pub fn n_degrees(&self) -> usize {
+        self.degree_cents.len()
+    }
+
+    /// Cents of a degree above degree 0, where degrees outside of the scale
+    /// wrap around to other periods.
+    fn cents_of_degree(&self, degree: i32) -> f32 {
+        let n = self.n_degrees() as i32;
+        self.degree_cents[degree.rem_euclid(n) as usize]
+            + degree.div_euclid(n) as f32 * self.period_cents
+    }
+
+    fn degree_of_note(&self, midi_note: i32) -> Option<i32> {
+        let offset = midi_note - self.middle_note;
+        match &self.mapping {
+            None => Some(offset),
+            Some(mapping) => {
+                if midi_note < mapping.first_note || midi_note > mapping.last_note {
+                    return None;
+                }
+                if mapping.degrees.is_empty() {
+                    return Some(offset);
+                }
+                let size = mapping.degrees.len() as i32;
+                mapping.degrees[offset.rem_euclid(size) as usize]
+                    .map(|degree| degree + offset.div_euclid(size) * mapping.octave_degree)
+            }
+        }
+    }
+
+    /// Frequency of a MIDI note in Hz, or `None` if the note is not mapped.
+    pub fn frequency(&self, midi_note: i32) -> Option<f32> {
+        let degree = self.degree_of_note(midi_note)?;
+        // If the reference note itself is unmapped, we treat it as degree 0.
+        let reference_degree = self.degree_of_note(self.reference_note).unwrap_or(0);
+        let cents = self.cents_of_degree(degree) - self.cents_of_degree(reference_degree);
+        Some(self.reference_frequency * 2.0f32.powf(cents / 1200.0))
+    }
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ScalaError {
+    #[error("Missing line: {0}")]
+    MissingLine(&'static str),
+    #[error("Could not parse {0}: {1:?}")]
+    InvalidValue(&'static str, String),
+    #[error("Expected {expected} pitches, got {actual}")]
+    WrongNumberOfPitches { expected: usize, actual: usize },
+}
+
+/// Lines of a Scala file without comments. Note that empty lines are kept because
+/// the description in .scl files may be empty.
+fn scala_lines(contents: &str) -> impl Iterator<Item = &str> {
+    contents
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.starts_with('!'))
+}
+
+/// A pitch line is either cents (if it contains a period) or a ratio like 3/2 or 2.
+fn parse_scala_pitch(line: &str) -> Result<f32, ScalaError> {
+    let token = line.split_whitespace().next().unwrap_or("");
+    let invalid = || ScalaError::InvalidValue("pitch", line.to_string());
+
+    if token.contains('.') {
+        return token.parse().map_err(|_| invalid());
+    }
+    let (numerator, denominator) = match token.split_once('/') {
+        Some((n, d)) => (n, d),
+        None => (token, "1"),
+    };
+    let numerator: f32 = numerator.parse().map_err(|_| invalid())?;
+    let denominator: f32 = denominator.parse().map_err(|_| invalid())?;
+    if numerator <= 0.0 || denominator <= 0.0 {
+        return Err(invalid());
+    }
+    Ok(ratio_to_cents(numerator / denominator))
+}
+
+fn parse_scala_int(line: Option<&str>, name: &'static str) -> Result<i32, ScalaError> {
+    let line = line.ok_or(ScalaError::MissingLine(name))?;
+    line.split_whitespace()
+        .next()
+        .unwrap_or("")
+        .parse()
+        .map_err(|_| ScalaError::InvalidValue(name, line.to_string()))
+}
+
+/// Parse a Scala `.scl` file into (degree cents, period cents).
+/// Format: http://www.huygens-fokker.org/scala/scl_format.html
+pub fn parse_scl(contents: &str) -> Result<(Vec<f32>, f32), ScalaError> {
+    let mut lines = scala_lines(contents);
+    lines.next().ok_or(ScalaError::MissingLine("description"))?;
+    let n_pitches = parse_scala_int(lines.next(), "number of notes")? as usize;
+
+    let pitches = lines
+        .filter(|line| !line.is_empty())
+        .map(parse_scala_pitch)
+        .collect::<Result<Vec<f32>, ScalaError>>()?;
+
+    if pitches.len() != n_pitches {
+        return Err(ScalaError::WrongNumberOfPitches {
+            expected: n_pitches,
+            actual: pitches.len(),
+        });
+    }
+
+    // The 1/1 is implicit in .scl files and the last pitch is the period.
+    match pitches.split_last() {
+        None => Ok((vec![0.0], 1200.0)),
+        Some((period, degrees)) => {
+            let mut degree_cents = vec![0.0];
+            degree_cents.extend_from_slice(degrees);
+            Ok((degree_cents, *period))
+        }
+    }
+}
+
+/// Parse a Scala `.kbm` file into (mapping, middle note, reference note, reference frequency).
+/// Format: http://www.huygens-fokker.org/scala/help.htm#mappings
+pub fn parse_kbm(contents: &str) -> Result<(KeyboardMapping, i32, i32, f32), ScalaError> {
+    let mut lines = scala_lines(contents).filter(|line| !line.is_empty());
+
+    let map_size = parse_scala_int(lines.next(), "map size")?;
+    let first_note = parse_scala_int(lines.next(), "first MIDI note")?;
+    let last_note = parse_scala_int(lines.next(), "last MIDI note")?;
+    let middle_note = parse_scala_int(lines.next(), "middle note")?;
+    let reference_note = parse_scala_int(lines.next(), "reference note")?;
+
+    let line = lines
+        .next()
+        .ok_or(ScalaError::MissingLine("reference frequency"))?;
+    let reference_frequency: f32 = line
+        .split_whitespace()
+        .next()
+        .unwrap_or("")
+        .parse()
+        .map_err(|_| ScalaError::InvalidValue("reference frequency", line.to_string()))?;
+
+    let octave_degree = parse_scala_int(lines.next(), "formal octave degree")?;
+
+    // Missing entries at the end of the mapping are unmapped.
+    let mut degrees: Vec<Option<i32>> = lines
+        .take(map_size.max(0) as usize)
+        .map(|line| match line.split_whitespace().next() {
+            Some("x") | None => Ok(None),
+            Some(_) => parse_scala_int(Some(line), "mapping entry").map(Some),
+        })
+        .collect::<Result<_, _>>()?;
+    degrees.resize(map_size.max(0) as usize, None);
+
+    Ok((
+        KeyboardMapping {
+            degrees,
+            octave_degree,
+            first_note,
+            last_note,
+        },
+        middle_note,
+        reference_note,
+        reference_frequency,
+    ))
+}
+
+/// Build a tuning from the contents of Scala files. Without a `.kbm` file, we use Scala's
+/// default mapping: degree 0 on middle C and A4 at `a4_frequency`. With one, its reference
+/// note and frequency are used and `a4_frequency` is ignored.
+pub fn tuning_from_scala(
+    scl: &str,
+    kbm: Option<&str>,
+    a4_frequency: f32,
+) -> Result<Tuning, ScalaError> {
+    let (degree_cents, period_cents) = parse_scl(scl)?;
+
+    let tuning = match kbm {
+        None => Tuning {
+            degree_cents,
+            period_cents,
+            middle_note: C4_MIDI_NOTE,
+            reference_note: A4_MIDI_NOTE,
+            reference_frequency: a4_frequency,
+            mapping: None,
+        },
+        Some(kbm) => {
+            let (mut mapping, middle_note, reference_note, reference_frequency) = parse_kbm(kbm)?;
+            // An octave degree of 0 means the size of the mapping, see the Scala docs.
+            if mapping.octave_degree == 0 {
+                mapping.octave_degree = mapping.degrees.len() as i32;
+            }
+            Tuning {
+                degree_cents,
+                period_cents,
+                middle_note,
+                reference_note,
+                reference_frequency,
+                mapping: Some(mapping),
+            }
+        }
+    };
+    Ok(tuning)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_temperaments() {
+        let tuning = Tuning::default();
+        assert_eq!(tuning.frequency(A4_MIDI_NOTE), Some(440.0));
+        assert!((tuning.frequency(C4_MIDI_NOTE).unwrap() - 261.63).abs() < 1e-2);
+
+        let tuning = Tuning::new(Temperament::Equal, NoteName::C, 432.0);
+        assert!((tuning.frequency(A4_MIDI_NOTE + 12).unwrap() - 864.0).abs() < 1e-2);
+
+        // In just intonation on A, the E above is a pure fifth
+        let tuning = Tuning::new(Temperament::JustIntonation, NoteName::A, 440.0);
+        assert!((tuning.frequency(A4_MIDI_NOTE + 7).unwrap() - 660.0).abs() < 1e-2);
+
+        // Quarter-comma meantone has pure major thirds
+        let tuning = Tuning::new(Temperament::QuarterCommaMeantone, NoteName::A, 440.0);
+        assert!((tuning.frequency(A4_MIDI_NOTE + 4).unwrap() - 550.0).abs() < 1e-2);
+
+        let tuning = Tuning::equal_division(24, NoteName::C, 440.0);
+        assert!(!tuning.is_twelve_tone());
+        let c4 = tuning.frequency(C4_MIDI_NOTE).unwrap();
+        assert!((c4 - 261.63).abs() < 1e-2);
+        // One step is a quarter tone
+        let ratio = tuning.frequency(C4_MIDI_NOTE + 1).unwrap() / c4;
+        assert!((ratio_to_cents(ratio) - 50.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn test_scala() {
+        let scl = "! example.scl
+!
+A pentatonic scale
+ 5
+!
+ 9/8
+  ! An indented comment
+ 5/4
+ 702.0 fifth
+ 5/3
+ 2
+";
+        let (degree_cents, period_cents) = parse_scl(scl).unwrap();
+        assert_eq!(degree_cents.len(), 5);
+        assert_eq!(degree_cents[3], 702.0);
+        assert_eq!(period_cents, 1200.0);
+
+        let kbm = "! Map the pentatonic scale to white keys, skipping F and B
+ 12
+ 0
+ 127
+ 60
+ 69
+ 440.0
+ 5
+! Mapping
+ 0
+ x
+ 1
+ x
+ 2
+ x
+ x
+ 3
+ x
+ 4
+";
+        let tuning = tuning_from_scala(scl, Some(kbm), 440.0).unwrap();
+        assert_eq!(tuning.frequency(A4_MIDI_NOTE), Some(440.0));
+        assert_eq!(tuning.frequency(61), None);
+        // Missing entries at the end are unmapped
+        assert_eq!(tuning.frequency(71), None);
+        // A4 is 5/3 above C4
+        assert!((tuning.frequency(60).unwrap() - 264.0).abs() < 1e-2);
+        assert!((tuning.frequency(72).unwrap() - 528.0).abs() < 1e-2);
+
+        assert_eq!(
+            parse_scl("bad\n 2\n 3/2\n"),
+            Err(ScalaError::WrongNumberOfPitches {
+                expected: 2,
+                actual: 1
+            })
+        );
+        assert!(parse_scl("bad\n 1\n abc\n").is_err());
+    }
+}
@@ -0,0 +1,309 @@
+use ndarray::prelude::*;
+use thiserror::Error;
+use wasm_bindgen::prelude::*;
+
+use crate::lpc::AnalysisConfig;
+
+/// Reflection coefficients are clamped to this so that the filter stays stable.
+const MAX_REFLECTION: f32 = 0.999;
+
+/// What drives the all-pole filter of the LPC vocoder.
+#[wasm_bindgen]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Excitation {
+    /// The residual of the LPC fit, which gives back (roughly) the original audio.
+    Residual,
+    /// A pulse train at a fixed pitch, for a robot voice.
+    PulseTrain,
+    /// White noise, for a whisper.
+    Noise,
+    /// An external signal, e.g. a synth, like a talkbox.
+    Carrier,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum VocoderError {
+    #[error("The carrier excitation needs a carrier signal")]
+    MissingCarrier,
+}
+
+/// Convert LPC coefficients [1, a_1, ..., a_p] of A(z) = 1 + sum a_i z^-i to the
+/// reflection coefficients k_1, ..., k_p using the step-down recursion.
+pub fn lpc_to_reflection(lpc_coefficients: ArrayView1<f32>) -> Vec<f32> {
+    let p = lpc_coefficients.len() - 1;
+    let mut a: Vec<f32> = lpc_coefficients.to_vec();
+    let mut reflection = vec![0.0; p];
+
+    for m in (1..=p).rev() {
+        let k = a[m].clamp(-MAX_REFLECTION, MAX_REFLECTION);
+        reflection[m - 1] = k;
+        let previous = a.clone();
+        for i in 1..m {
+            a[i] = (previous[i] - k * previous[m - i]) / (1.0 - k * k);
+        }
+    }
+    reflection
+}
+
+/// Resynthesizes audio from LPC coefficients through an all-pole lattice filter driven
+/// by an excitation signal. The reflection coefficients and the gain are interpolated
+/// linearly within each hop, which keeps the filter stable and avoids clicks at frame
+/// boundaries. The state is kept between calls so the audio can be processed in chunks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LpcVocoder {
+    previous_reflection: Vec<f32>,
+    previous_gain: f32,
+    /// Backward prediction errors of the lattice from the previous sample.
+    lattice_state: Vec<f32>,
+    de_emphasis_state: f32,
+    pulse_phase: f32,
+    noise_state: u32,
+}
+
+impl Default for LpcVocoder {
+    fn default() -> Self {
+        LpcVocoder {
+            previous_reflection: Vec::new(),
+            previous_gain: 0.0,
+            lattice_state: Vec::new(),
+            de_emphasis_state: 0.0,
+            pulse_phase: 0.0,
+            noise_state: 0x9E3779B9,
+        }
+    }
+}
+
+impl LpcVocoder {
+    pub fn reset(&mut self) {
+        *self = LpcVocoder::default();
+    }
+
+    /// `lpc_coefficients` and `gain` are as returned by `fit_lpc()` with the given config.
+    /// `excitation` should have roughly unit RMS and at least `n_hops * hop_size` samples.
+    pub fn process(
+        &mut self,
+        lpc_coefficients: ArrayView2<f32>,
+        gain: ArrayView1<f32>,
+        excitation: ArrayView1<f32>,
+        hop_size: usize,
+        config: &AnalysisConfig,
+    ) -> Array1<f32> {
+        let n_hops = lpc_coefficients.len_of(Axis(0));
+        let p = lpc_coefficients.len_of(Axis(1)) - 1;
+        assert!(excitation.len() >= n_hops * hop_size);
+
+        if self.lattice_state.len() != p {
+            // The order changed, the old state is meaningless
+            self.previous_reflection = vec![0.0; p];
+            self.lattice_state = vec![0.0; p];
+        }
+
+        // fit_lpc() measures the gain on windowed audio, undo the window's attenuation
+        let window_rms = window_rms(config, hop_size);
+
+        let mut output = Array1::zeros(n_hops * hop_size);
+        for hop in 0..n_hops {
+            let coefficients = lpc_coefficients.row(hop);
+            // Frames where the fit failed are left as zeros by fit_lpc()
+            let (reflection, hop_gain) = if coefficients[0] == 0.0 {
+                (vec![0.0; p], 0.0)
+            } else {
+                (lpc_to_reflection(coefficients), gain[hop] / window_rms)
+            };
+
+            for j in 0..hop_size {
+                let t = (j + 1) as f32 / hop_size as f32;
+                let cur_gain = self.previous_gain + t * (hop_gain - self.previous_gain);
+                let x = excitation[hop * hop_size + j] * cur_gain;
+
+                // All-pole lattice: go from the highest order down to order 0
+                let mut f = x;
+                for m in (0..p).rev() {
+                    let k = self.previous_reflection[m]
+                        + t * (reflection[m] - self.previous_reflection[m]);
+                    f -= k * self.lattice_state[m];
+                    if m + 1 < p {
+                        self.lattice_state[m + 1] = k * f + self.lattice_state[m];
+                    }
+                }
+                self.lattice_state[0] = f;
+
+                // Undo the pre-emphasis of the analysis
+                self.de_emphasis_state = f + config.pre_emphasis * self.de_emphasis_state;
+                output[hop * hop_size + j] = self.de_emphasis_state;
+            }
+
+            self.previous_reflection = reflection;
+            self.previous_gain = hop_gain;
+        }
+        output
+    }
+
+    /// Unit-RMS pulses at `frequency_hz`. The phase continues across calls.
+    /// A frequency that isn't positive (or isn't finite) means unvoiced, which gives noise.
+    pub fn pulse_train(
+        &mut self,
+        n_samples: usize,
+        frequency_hz: f32,
+        sample_rate: usize,
+    ) -> Array1<f32> {
+        if !frequency_hz.is_finite() || frequency_hz <= 0.0 {
+            return self.noise(n_samples);
+        }
+        let period = sample_rate as f32 / frequency_hz;
+        let amplitude = period.sqrt();
+        Array1::from_iter((0..n_samples).map(|_| {
+            self.pulse_phase += 1.0;
+            if self.pulse_phase >= period {
+                self.pulse_phase -= period;
+                amplitude
+            } else {
+                0.0
+            }
+        }))
+    }
+
+    /// Unit-RMS white noise from a xorshift generator.
+    pub fn noise(&mut self, n_samples: usize) -> Array1<f32> {
+        Array1::from_iter((0..n_samples).map(|_| {
+            self.noise_state ^= self.noise_state << 13;
+            self.noise_state ^= self.noise_state >> 17;
+            self.noise_state ^= self.noise_state << 5;
+            let uniform = self.noise_state as f32 / u32::MAX as f32;
+            (uniform * 2.0 - 1.0) * 3.0f32.sqrt()
+        }))
+    }
+}
+
+fn window_rms(config: &AnalysisConfig, hop_size: usize) -> f32 {
+    config
+        .window(hop_size)
+        .mapv(|x| x * x)
+        .mean()
+        .unwrap_or(1.0)
+        .sqrt()
+}
+
+/// The carrier excitation: `carrier` zero-padded (or truncated) to `n_samples` and
+/// normalized with `normalize_rms_per_hop()`.
+pub fn carrier_excitation(
+    carrier: Option<&[f32]>,
+    n_samples: usize,
+    hop_size: usize,
+) -> Result<Array1<f32>, VocoderError> {
+    let carrier = carrier.ok_or(VocoderError::MissingCarrier)?;
+    let padded = Array1::from_iter((0..n_samples).map(|i| carrier.get(i).copied().unwrap_or(0.0)));
+    Ok(normalize_rms_per_hop(padded.view(), hop_size))
+}
+
+/// Scale each hop of `signal` to unit RMS so that the loudness of the output follows
+/// the analyzed audio and not the carrier.
+pub fn normalize_rms_per_hop(signal: ArrayView1<f32>, hop_size: usize) -> Array1<f32> {
+    let mut result = signal.to_owned();
+    for mut chunk in result.exact_chunks_mut(hop_size) {
+        let rms = chunk.mapv(|x| x * x).mean().unwrap().sqrt();
+        if rms > 1e-6 {
+            chunk /= rms;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signal_processing::tests::assert_array1_eq;
+    use ndarray::Zip;
+
+    /// Direct-form all-pole filter 1 / A(z) to compare against.
+    fn all_pole(lpc_coefficients: &[f32], signal: &Array1<f32>) -> Array1<f32> {
+        let mut output: Array1<f32> = Array1::zeros(signal.len());
+        for n in 0..signal.len() {
+            let mut y = signal[n];
+            for (i, a) in lpc_coefficients.iter().enumerate().skip(1) {
+                if n >= i {
+                    y -= a * output[n - i];
+                }
+            }
+            output[n] = y;
+        }
+        output
+    }
+
+    #[test]
+    fn test_lattice_matches_direct_form() {
+        // A stable filter with resonances, (1 - 0.9z^-1)(1 - 1.2z^-1 + 0.8z^-2)
+        let lpc_coefficients = [1.0, -2.1, 1.88, -0.72];
+        let reflection = lpc_to_reflection(ArrayView1::from(&lpc_coefficients));
+        assert!(reflection.iter().all(|k| k.abs() < 1.0));
+
+        let hop_size = 16;
+        let n_hops = 4;
+        let lpc = Array2::from_shape_fn((n_hops, 4), |(_, i)| lpc_coefficients[i]);
+        let gain = Array1::ones(n_hops);
+        let mut vocoder = LpcVocoder::default();
+        let excitation = vocoder.noise(n_hops * hop_size);
+
+        // Skip the first hop, where the coefficients are still being interpolated from zero
+        let config = AnalysisConfig::default();
+        let mut vocoder = LpcVocoder {
+            previous_reflection: reflection,
+            previous_gain: gain[0] / window_rms(&config, hop_size),
+            lattice_state: vec![0.0; 3],
+            ..LpcVocoder::default()
+        };
+        let output = vocoder.process(
+            lpc.view(),
+            gain.view(),
+            excitation.view(),
+            hop_size,
+            &config,
+        );
+
+        let scaled = &excitation * vocoder.previous_gain;
+        let expected = all_pole(&lpc_coefficients, &scaled);
+        let mut de_emphasized = Array1::zeros(expected.len());
+        let mut state = 0.0;
+        Zip::from(&mut de_emphasized)
+            .and(&expected)
+            .for_each(|out, &x| {
+                state = x + config.pre_emphasis * state;
+                *out = state;
+            });
+        assert_array1_eq(&output, &de_emphasized, 1e-3);
+    }
+
+    #[test]
+    fn test_excitation_is_unit_rms() {
+        let mut vocoder = LpcVocoder::default();
+        let rms = |x: &Array1<f32>| x.mapv(|x| x * x).mean().unwrap().sqrt();
+
+        assert!((rms(&vocoder.noise(10000)) - 1.0).abs() < 0.05);
+        assert!((rms(&vocoder.pulse_train(8000, 100.0, 8000)) - 1.0).abs() < 0.05);
+
+        let carrier = Array1::from_iter((0..64).map(|i| (i as f32 * 0.3).sin() * 5.0));
+        let normalized = normalize_rms_per_hop(carrier.view(), 32);
+        assert!((rms(&normalized.slice(s![..32]).to_owned()) - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_invalid_excitation_settings() {
+        // Unvoiced pulse trains are noise
+        let mut vocoder = LpcVocoder::default();
+        for frequency_hz in [0.0, -100.0, f32::NAN] {
+            let pulses = vocoder.pulse_train(1000, frequency_hz, 8000);
+            assert!(pulses.iter().all(|x| x.is_finite()));
+            assert!(pulses.iter().filter(|x| **x != 0.0).count() > 900);
+        }
+
+        // A short carrier is zero-padded, a missing one is an error
+        let carrier: Vec<f32> = (0..48).map(|i| (i as f32 * 0.3).sin()).collect();
+        let excitation = carrier_excitation(Some(&carrier), 64, 16).unwrap();
+        assert_eq!(excitation.len(), 64);
+        assert!(excitation.slice(s![48..]).iter().all(|x| *x == 0.0));
+        assert_eq!(
+            carrier_excitation(None, 64, 16),
+            Err(VocoderError::MissingCarrier)
+        );
+    }
+}